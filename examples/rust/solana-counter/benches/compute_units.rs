@@ -0,0 +1,575 @@
+//! Compute-unit report for every counter instruction.
+//!
+//! Runs each instruction once against the SBF build of the program and
+//! records the compute units it consumed. The report is written to
+//! `target/compute-units.json` and compared against
+//! `benches/compute_units.baseline.json`; the run fails if any instruction
+//! uses more than the baseline plus `COMPUTE_UNITS_TOLERANCE` percent
+//! (default 5), or has no baseline to compare against.
+//!
+//! ```bash
+//! cargo build-sbf
+//! cargo bench --bench compute_units
+//! cargo bench --bench compute_units -- --update-baseline
+//! ```
+//!
+//! Native `solana-program-test` builtins consume a fixed single unit, so
+//! the harness refuses to run without `counter_program.so`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use common::{
+    clock, create_associated_token_account, create_mint, create_mint_with_authority,
+    create_token_account, fetch, funded_keypair, layout_data, send_all, setup, store_account,
+    store_metadata,
+};
+use counter_client::{
+    accept_authority, add_operator, apply_ops, cancel_authority_transfer, cancel_reset, close,
+    close_contribution, close_history, close_with_fee, compare_and_set, configure, counter_address,
+    decrement, decrement_by, execute_reset, increment, increment_by, increment_with_fee,
+    increment_with_permit, increment_with_tokens, init_history, initialize, initialize_with_fee,
+    migrate, migrate_counter, pause, propose_authority, remove_operator, reset, schedule_reset,
+    set_collection_gate, set_cooldown, set_gate, set_milestones, set_require_scheduled_reset,
+    set_window, unpause, with_history, withdraw_fees, OperatorTokens, PROGRAM_ID,
+};
+use counter_program::{
+    Counter, CounterConfig, CounterOp, MilestoneConfig, OverflowPolicy, Permit, RollingWindow,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Allowed growth over the baseline, in percent, unless overridden
+const DEFAULT_TOLERANCE: u64 = 5;
+
+type Report = BTreeMap<String, u64>;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let baseline_path = manifest_dir.join("benches/compute_units.baseline.json");
+    let report_path = manifest_dir.join("target/compute-units.json");
+    let update_baseline = std::env::args().any(|arg| arg == "--update-baseline");
+
+    // `solana-program-test` loads the SBF build instead of the native
+    // processor whenever `SBF_OUT_DIR` is set
+    if std::env::var_os("SBF_OUT_DIR").is_none() && std::env::var_os("BPF_OUT_DIR").is_none() {
+        std::env::set_var("SBF_OUT_DIR", manifest_dir.join("target/deploy"));
+    }
+    if !sbf_program().is_some_and(|path| path.is_file()) {
+        eprintln!("counter_program.so not found; run `cargo build-sbf` first");
+        return ExitCode::FAILURE;
+    }
+
+    let report = measure().await;
+
+    for (instruction, units) in &report {
+        println!("{instruction:<28} {units:>8} CU");
+    }
+    write_report(&report_path, &report);
+    println!("report written to {}", report_path.display());
+
+    if update_baseline {
+        write_report(&baseline_path, &report);
+        println!("baseline updated at {}", baseline_path.display());
+        return ExitCode::SUCCESS;
+    }
+
+    let Ok(baseline) = std::fs::read_to_string(&baseline_path) else {
+        eprintln!(
+            "no baseline at {}; pass --update-baseline to record one",
+            baseline_path.display()
+        );
+        return ExitCode::FAILURE;
+    };
+    let baseline: Report = serde_json::from_str(&baseline).expect("malformed baseline");
+    let tolerance = std::env::var("COMPUTE_UNITS_TOLERANCE")
+        .map(|value| {
+            value
+                .parse()
+                .expect("COMPUTE_UNITS_TOLERANCE must be a whole percentage")
+        })
+        .unwrap_or(DEFAULT_TOLERANCE);
+
+    let regressions = compare(&baseline, &report, tolerance);
+    if regressions.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for regression in regressions {
+        eprintln!("regression: {regression}");
+    }
+    ExitCode::FAILURE
+}
+
+/// Where `solana-program-test` will look for the SBF build
+fn sbf_program() -> Option<PathBuf> {
+    let dir = std::env::var_os("SBF_OUT_DIR").or_else(|| std::env::var_os("BPF_OUT_DIR"))?;
+    Some(PathBuf::from(dir).join("counter_program.so"))
+}
+
+/// Describe every instruction that grew past the baseline by more than
+/// `tolerance` percent, or that has no baseline entry to compare against
+fn compare(baseline: &Report, report: &Report, tolerance: u64) -> Vec<String> {
+    let mut regressions = Vec::new();
+    for (instruction, &units) in report {
+        let Some(&expected) = baseline.get(instruction) else {
+            regressions.push(format!(
+                "{instruction} consumed {units} CU but has no baseline; \
+                 pass --update-baseline to record it"
+            ));
+            continue;
+        };
+        let limit = expected + expected * tolerance / 100;
+        if units > limit {
+            regressions.push(format!(
+                "{instruction} consumed {units} CU, baseline {expected} CU (limit {limit} CU)"
+            ));
+        }
+    }
+    regressions
+}
+
+fn write_report(path: &Path, report: &Report) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    let mut json = serde_json::to_string_pretty(report).unwrap();
+    json.push('\n');
+    std::fs::write(path, json).unwrap();
+}
+
+/// Run every instruction once and record its compute units
+async fn measure() -> Report {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, _) = counter_address(&payer, 0);
+    let operator = funded_keypair(&mut context).await;
+    let pending = Keypair::new();
+    let config = CounterConfig {
+        min: 0,
+        max: 1_000,
+        overflow_policy: OverflowPolicy::Error,
+    };
+
+    let mut report = Report::new();
+    let mut run = |name: &str, units: u64| {
+        report.insert(name.to_string(), units);
+    };
+
+    run(
+        "initialize",
+        units(&mut context, initialize(&payer, 0, config), &[]).await,
+    );
+    run(
+        "increment",
+        units(&mut context, increment(&counter, &payer, None), &[]).await,
+    );
+    run(
+        "increment_expected",
+        units(&mut context, increment(&counter, &payer, Some(1)), &[]).await,
+    );
+    run(
+        "decrement",
+        units(&mut context, decrement(&counter, &payer, None), &[]).await,
+    );
+    run(
+        "decrement_expected",
+        units(&mut context, decrement(&counter, &payer, Some(1)), &[]).await,
+    );
+    run(
+        "increment_by",
+        units(&mut context, increment_by(&counter, &payer, 10), &[]).await,
+    );
+    run(
+        "decrement_by",
+        units(&mut context, decrement_by(&counter, &payer, 5), &[]).await,
+    );
+    run(
+        "compare_and_set",
+        units(&mut context, compare_and_set(&counter, &payer, 5, 7), &[]).await,
+    );
+    run(
+        "reset",
+        units(&mut context, reset(&counter, &payer), &[]).await,
+    );
+    run(
+        "schedule_reset",
+        units(&mut context, schedule_reset(&counter, &payer, 0), &[]).await,
+    );
+    run(
+        "execute_reset",
+        units(&mut context, execute_reset(&counter, &payer), &[]).await,
+    );
+    units(&mut context, schedule_reset(&counter, &payer, 60), &[]).await;
+    run(
+        "cancel_reset",
+        units(&mut context, cancel_reset(&counter, &payer), &[]).await,
+    );
+    // Left off so the batch below can still reset
+    run(
+        "set_require_scheduled_reset",
+        units(
+            &mut context,
+            set_require_scheduled_reset(&counter, &payer, false),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "apply_ops",
+        units(
+            &mut context,
+            apply_ops(
+                &counter,
+                &payer,
+                vec![
+                    CounterOp::IncrementBy(10),
+                    CounterOp::Decrement,
+                    CounterOp::Set(5),
+                    CounterOp::Reset,
+                ],
+                true,
+            ),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "configure",
+        units(&mut context, configure(&counter, &payer, config), &[]).await,
+    );
+    run(
+        "add_operator",
+        units(
+            &mut context,
+            add_operator(&counter, &payer, operator.pubkey()),
+            &[],
+        )
+        .await,
+    );
+    // Limits only the payer, whose contribution record already exists, so
+    // the operator's first increment still passes the cooldown check
+    run(
+        "set_cooldown",
+        units(&mut context, set_cooldown(&counter, &payer, 0, 1_000), &[]).await,
+    );
+    run(
+        "set_window",
+        units(
+            &mut context,
+            set_window(&counter, &payer, Some(RollingWindow::Seconds(86_400))),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "increment_operator",
+        units(
+            &mut context,
+            increment(&counter, &operator.pubkey(), None),
+            &[&operator],
+        )
+        .await,
+    );
+    let clock = clock(&mut context).await;
+    let permit = Permit {
+        counter,
+        nonce: 0,
+        delta: 1,
+        expiry: clock.unix_timestamp + 60,
+    };
+    let signature = context.payer.sign_message(&permit.message());
+    run(
+        "increment_with_permit",
+        transaction_units(
+            &mut context,
+            &increment_with_permit(&payer, &permit, &signature),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "remove_operator",
+        units(
+            &mut context,
+            remove_operator(&counter, &payer, operator.pubkey()),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "pause",
+        units(&mut context, pause(&counter, &payer), &[]).await,
+    );
+    run(
+        "unpause",
+        units(&mut context, unpause(&counter, &payer), &[]).await,
+    );
+    run(
+        "propose_authority",
+        units(
+            &mut context,
+            propose_authority(&counter, &payer, pending.pubkey()),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "cancel_authority_transfer",
+        units(
+            &mut context,
+            cancel_authority_transfer(&counter, &payer),
+            &[],
+        )
+        .await,
+    );
+    units(
+        &mut context,
+        propose_authority(&counter, &payer, pending.pubkey()),
+        &[],
+    )
+    .await;
+    run(
+        "accept_authority",
+        units(
+            &mut context,
+            accept_authority(&counter, &pending.pubkey()),
+            &[&pending],
+        )
+        .await,
+    );
+    units(
+        &mut context,
+        propose_authority(&counter, &pending.pubkey(), payer),
+        &[&pending],
+    )
+    .await;
+    units(&mut context, accept_authority(&counter, &payer), &[]).await;
+    run(
+        "close",
+        units(&mut context, close(&counter, &payer), &[]).await,
+    );
+
+    let mint = create_mint(&mut context).await;
+    let source = create_token_account(&mut context, &mint, &payer, 10).await;
+    let (fee_counter, _) = counter_address(&payer, 1);
+    run(
+        "initialize_with_fee",
+        units(
+            &mut context,
+            initialize_with_fee(&payer, 1, config, &mint, 5),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "increment_with_fee",
+        units(
+            &mut context,
+            increment_with_fee(&fee_counter, &payer, None, &source),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "set_gate",
+        units(
+            &mut context,
+            set_gate(&fee_counter, &payer, Some(mint), 1),
+            &[],
+        )
+        .await,
+    );
+    let tokens = OperatorTokens {
+        fee_source: Some(source),
+        gate_token_account: Some(source),
+        ..OperatorTokens::default()
+    };
+    run(
+        "increment_gated",
+        units(
+            &mut context,
+            increment_with_tokens(&fee_counter, &payer, None, tokens),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "withdraw_fees",
+        units(
+            &mut context,
+            withdraw_fees(&fee_counter, &payer, &source, 10),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "close_with_fee",
+        units(&mut context, close_with_fee(&fee_counter, &payer), &[]).await,
+    );
+    run(
+        "close_contribution",
+        units(&mut context, close_contribution(&fee_counter, &payer), &[]).await,
+    );
+
+    let (reward_counter, _) = counter_address(&payer, 2);
+    let reward_mint = create_mint_with_authority(&mut context, &reward_counter).await;
+    create_associated_token_account(&mut context, &reward_mint, &payer).await;
+    units(&mut context, initialize(&payer, 2, config), &[]).await;
+    let milestones = vec![MilestoneConfig {
+        threshold: 1,
+        reward: 10,
+    }];
+    run(
+        "set_milestones",
+        units(
+            &mut context,
+            set_milestones(&reward_counter, &payer, Some(reward_mint), milestones),
+            &[],
+        )
+        .await,
+    );
+    let tokens = OperatorTokens {
+        reward_mint: Some(reward_mint),
+        ..OperatorTokens::default()
+    };
+    run(
+        "increment_milestone",
+        units(
+            &mut context,
+            increment_with_tokens(&reward_counter, &payer, None, tokens),
+            &[],
+        )
+        .await,
+    );
+
+    let (history_counter, _) = counter_address(&payer, 3);
+    units(&mut context, initialize(&payer, 3, config), &[]).await;
+    run(
+        "init_history",
+        units(&mut context, init_history(&history_counter, &payer), &[]).await,
+    );
+    run(
+        "increment_history",
+        units(
+            &mut context,
+            with_history(increment(&history_counter, &payer, None), &history_counter),
+            &[],
+        )
+        .await,
+    );
+    run(
+        "close_history",
+        units(&mut context, close_history(&history_counter, &payer), &[]).await,
+    );
+
+    let (nft_counter, _) = counter_address(&payer, 4);
+    units(&mut context, initialize(&payer, 4, config), &[]).await;
+    let collection = Pubkey::new_unique();
+    run(
+        "set_collection_gate",
+        units(
+            &mut context,
+            set_collection_gate(&nft_counter, &payer, Some(collection)),
+            &[],
+        )
+        .await,
+    );
+    let nft = create_mint(&mut context).await;
+    let tokens = OperatorTokens {
+        gate_token_account: Some(create_token_account(&mut context, &nft, &payer, 1).await),
+        gate_metadata: Some(store_metadata(&mut context, &nft, &collection, true)),
+        ..OperatorTokens::default()
+    };
+    run(
+        "increment_collection_gated",
+        units(
+            &mut context,
+            increment_with_tokens(&nft_counter, &payer, None, tokens),
+            &[],
+        )
+        .await,
+    );
+
+    let template = fetch(&mut context, &history_counter).await;
+    let creator = Pubkey::new_unique();
+    let legacy_counter = store_legacy_counter(&mut context, &template, &creator);
+    run(
+        "migrate_counter",
+        units(
+            &mut context,
+            migrate_counter(&legacy_counter, &payer, creator, 0),
+            &[],
+        )
+        .await,
+    );
+    let unversioned_counter = store_unversioned_counter(&mut context, &template, &creator);
+    run(
+        "migrate",
+        units(&mut context, migrate(&unversioned_counter, &payer), &[]).await,
+    );
+
+    report
+}
+
+/// Send `instruction`, which must succeed, and return the compute units it consumed
+async fn units(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> u64 {
+    transaction_units(context, &[instruction], signers).await
+}
+
+/// Send `instructions` in one transaction, which must succeed, and return the
+/// compute units they consumed
+async fn transaction_units(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> u64 {
+    let outcome = send_all(context, instructions, signers).await;
+    if let Err(err) = outcome.result {
+        panic!("transaction failed: {err}\n{}", outcome.logs.join("\n"));
+    }
+    outcome.compute_units_consumed
+}
+
+/// Store `template`, a counter owned by the payer, in the baseline layout at
+/// `creator`'s legacy address
+fn store_legacy_counter(
+    context: &mut ProgramTestContext,
+    template: &Counter,
+    creator: &Pubkey,
+) -> Pubkey {
+    let (address, bump) =
+        Pubkey::find_program_address(&[b"counter", creator.as_ref()], &PROGRAM_ID);
+    let mut counter = template.clone();
+    counter.bump = bump;
+    counter.creator = *creator;
+    let data = layout_data(&counter, Counter::LEGACY_LAYOUTS[0]);
+    store_account(context, &address, &data, &PROGRAM_ID);
+    address
+}
+
+/// Store `template` in the oldest id-addressed layout as `creator`'s counter 1
+fn store_unversioned_counter(
+    context: &mut ProgramTestContext,
+    template: &Counter,
+    creator: &Pubkey,
+) -> Pubkey {
+    let (address, bump) = counter_address(creator, 1);
+    let mut counter = template.clone();
+    counter.bump = bump;
+    counter.creator = *creator;
+    counter.counter_id = 1;
+    let data = layout_data(&counter, Counter::LAYOUTS[0]);
+    store_account(context, &address, &data, &PROGRAM_ID);
+    address
+}
@@ -0,0 +1,289 @@
+//! Decoder for the events `counter_program` emits.
+//!
+//! `emit!` logs each event as `Program data: <base64>`, where the payload is
+//! the event's 8-byte discriminator followed by its Borsh encoding. Only lines
+//! logged while the counter program is executing are decoded, so data from
+//! other programs invoked in the same transaction is ignored.
+//!
+//! Built with the `event-cpi` feature, the program instead emits each event
+//! as a self-CPI whose instruction data is Anchor's event tag followed by the
+//! same payload. Those events are recovered from the transaction's inner
+//! instructions, which survive log truncation.
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use counter_program::{
+    AuthorityAccepted, AuthorityProposed, AuthorityTransferCancelled, CollectionGateConfigured,
+    CooldownConfigured, CounterClosed, CounterConfigured, CounterMigrated, CounterOpsApplied,
+    CounterPaused, CounterReset, CounterUnpaused, CounterUpdated, CounterUpgraded, FeesWithdrawn,
+    GateConfigured, HistoryClosed, HistoryInitialized, MilestoneReached, MilestonesConfigured,
+    OperatorAdded, OperatorRemoved, ResetCancelled, ResetLockConfigured, ResetScheduled,
+    WindowClosed, WindowConfigured,
+};
+use solana_sdk::bs58;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+    UiParsedInstruction,
+};
+
+use crate::PROGRAM_ID;
+
+/// Any event emitted by the counter program
+#[derive(Debug, Clone)]
+pub enum CounterEvent {
+    Updated(CounterUpdated),
+    OpsApplied(CounterOpsApplied),
+    Reset(CounterReset),
+    ResetScheduled(ResetScheduled),
+    ResetCancelled(ResetCancelled),
+    ResetLockConfigured(ResetLockConfigured),
+    Paused(CounterPaused),
+    Unpaused(CounterUnpaused),
+    OperatorAdded(OperatorAdded),
+    OperatorRemoved(OperatorRemoved),
+    Configured(CounterConfigured),
+    FeesWithdrawn(FeesWithdrawn),
+    GateConfigured(GateConfigured),
+    CollectionGateConfigured(CollectionGateConfigured),
+    CooldownConfigured(CooldownConfigured),
+    WindowConfigured(WindowConfigured),
+    WindowClosed(WindowClosed),
+    MilestonesConfigured(MilestonesConfigured),
+    MilestoneReached(MilestoneReached),
+    HistoryInitialized(HistoryInitialized),
+    HistoryClosed(HistoryClosed),
+    AuthorityProposed(AuthorityProposed),
+    AuthorityAccepted(AuthorityAccepted),
+    AuthorityTransferCancelled(AuthorityTransferCancelled),
+    Closed(CounterClosed),
+    Migrated(CounterMigrated),
+    Upgraded(CounterUpgraded),
+}
+
+/// A counter event together with the transaction that emitted it
+#[derive(Debug, Clone)]
+pub struct DecodedEvent {
+    pub slot: u64,
+    pub signature: Signature,
+    pub event: CounterEvent,
+}
+
+/// Decode a raw event payload (discriminator followed by Borsh data)
+pub fn decode_event(data: &[u8]) -> Option<CounterEvent> {
+    let (discriminator, mut payload) = (data.get(..8)?, data.get(8..)?);
+
+    macro_rules! decode {
+        ($($event:ident => $variant:ident),* $(,)?) => {
+            $(
+                if discriminator == $event::DISCRIMINATOR {
+                    return $event::deserialize(&mut payload).ok().map(CounterEvent::$variant);
+                }
+            )*
+        };
+    }
+
+    decode! {
+        CounterUpdated => Updated,
+        CounterOpsApplied => OpsApplied,
+        CounterReset => Reset,
+        ResetScheduled => ResetScheduled,
+        ResetCancelled => ResetCancelled,
+        ResetLockConfigured => ResetLockConfigured,
+        CounterPaused => Paused,
+        CounterUnpaused => Unpaused,
+        OperatorAdded => OperatorAdded,
+        OperatorRemoved => OperatorRemoved,
+        CounterConfigured => Configured,
+        FeesWithdrawn => FeesWithdrawn,
+        GateConfigured => GateConfigured,
+        CollectionGateConfigured => CollectionGateConfigured,
+        CooldownConfigured => CooldownConfigured,
+        WindowConfigured => WindowConfigured,
+        WindowClosed => WindowClosed,
+        MilestonesConfigured => MilestonesConfigured,
+        MilestoneReached => MilestoneReached,
+        HistoryInitialized => HistoryInitialized,
+        HistoryClosed => HistoryClosed,
+        AuthorityProposed => AuthorityProposed,
+        AuthorityAccepted => AuthorityAccepted,
+        AuthorityTransferCancelled => AuthorityTransferCancelled,
+        CounterClosed => Closed,
+        CounterMigrated => Migrated,
+        CounterUpgraded => Upgraded,
+    }
+
+    None
+}
+
+/// Decode the data of a self-CPI event instruction (event tag followed by a raw event payload)
+pub fn decode_event_cpi(data: &[u8]) -> Option<CounterEvent> {
+    decode_event(data.strip_prefix(&EVENT_IX_TAG_LE)?)
+}
+
+/// Extract the counter events from a transaction's inner instructions, given
+/// as `(program_id, data)` pairs in order. Instructions for other programs
+/// are ignored.
+pub fn parse_inner_instructions<'a>(
+    instructions: impl IntoIterator<Item = (&'a Pubkey, &'a [u8])>,
+) -> Vec<CounterEvent> {
+    instructions
+        .into_iter()
+        .filter(|(program_id, _)| **program_id == PROGRAM_ID)
+        .filter_map(|(_, data)| decode_event_cpi(data))
+        .collect()
+}
+
+/// Extract the counter events from a transaction's log messages, in order
+pub fn parse_logs(logs: &[String]) -> Vec<CounterEvent> {
+    let program_id = PROGRAM_ID.to_string();
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if invocations.last() == Some(&program_id.as_str()) {
+                events.extend(
+                    STANDARD
+                        .decode(data)
+                        .ok()
+                        .and_then(|bytes| decode_event(&bytes)),
+                );
+            }
+            continue;
+        }
+
+        // Track the invocation stack from "Program <id> invoke [n]" and
+        // "Program <id> success" / "Program <id> failed: ..." lines
+        match rest.split_once(' ') {
+            Some((id, tail)) if !id.ends_with(':') => {
+                if tail.starts_with("invoke [") {
+                    invocations.push(id);
+                } else if tail == "success" || tail.starts_with("failed") {
+                    invocations.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Decode the events in a transaction's logs, tagging each with its slot and signature
+pub fn decode_logs(slot: u64, signature: Signature, logs: &[String]) -> Vec<DecodedEvent> {
+    parse_logs(logs)
+        .into_iter()
+        .map(|event| DecodedEvent {
+            slot,
+            signature,
+            event,
+        })
+        .collect()
+}
+
+/// Decode the events of a transaction fetched with `getTransaction`, from
+/// its logs and, for `event-cpi` builds, its inner instructions.
+///
+/// Failed transactions are rolled back, so they yield no events.
+pub fn decode_transaction(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Vec<DecodedEvent> {
+    let Some(meta) = &transaction.transaction.meta else {
+        return Vec::new();
+    };
+    if meta.err.is_some() {
+        return Vec::new();
+    }
+
+    let encoded = &transaction.transaction.transaction;
+    let signature = match encoded {
+        EncodedTransaction::Json(ui) => ui.signatures.first().and_then(|sig| sig.parse().ok()),
+        encoded => encoded
+            .decode()
+            .and_then(|tx| tx.signatures.first().copied()),
+    };
+    let Some(signature) = signature else {
+        return Vec::new();
+    };
+
+    let mut events = match &meta.log_messages {
+        OptionSerializer::Some(logs) => parse_logs(logs),
+        _ => Vec::new(),
+    };
+
+    if let OptionSerializer::Some(inner_instructions) = &meta.inner_instructions {
+        let mut account_keys = account_keys(encoded);
+        if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+            account_keys.extend(loaded.writable.iter().chain(&loaded.readonly).cloned());
+        }
+
+        let instructions: Vec<(Pubkey, Vec<u8>)> = inner_instructions
+            .iter()
+            .flat_map(|inner| &inner.instructions)
+            .filter_map(|instruction| {
+                let (program_id, data) = match instruction {
+                    UiInstruction::Compiled(compiled) => (
+                        account_keys.get(usize::from(compiled.program_id_index))?,
+                        &compiled.data,
+                    ),
+                    UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => {
+                        (&decoded.program_id, &decoded.data)
+                    }
+                    UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => return None,
+                };
+                Some((
+                    program_id.parse().ok()?,
+                    bs58::decode(data).into_vec().ok()?,
+                ))
+            })
+            .collect();
+
+        events.extend(parse_inner_instructions(
+            instructions
+                .iter()
+                .map(|(program_id, data)| (program_id, data.as_slice())),
+        ));
+    }
+
+    events
+        .into_iter()
+        .map(|event| DecodedEvent {
+            slot: transaction.slot,
+            signature,
+            event,
+        })
+        .collect()
+}
+
+/// The account keys stored in the transaction message, as base58 strings
+fn account_keys(transaction: &EncodedTransaction) -> Vec<String> {
+    match transaction {
+        EncodedTransaction::Json(ui) => match &ui.message {
+            UiMessage::Raw(message) => message.account_keys.clone(),
+            UiMessage::Parsed(message) => message
+                .account_keys
+                .iter()
+                .map(|account| account.pubkey.clone())
+                .collect(),
+        },
+        encoded => encoded
+            .decode()
+            .map(|tx| {
+                tx.message
+                    .static_account_keys()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,923 @@
+//! Off-chain client for the counter program.
+//!
+//! Derives counter addresses, builds instructions, decodes `Counter`,
+//! `Contribution` and `History` accounts and maps failed transactions back
+//! to typed [`CounterError`] values. Both
+//! `solana-program-test` (through [`BanksClient`]) and a regular RPC node
+//! (through [`RpcClient`]) are supported. Events emitted by the program can
+//! be recovered from transaction logs, or from inner instructions for
+//! `event-cpi` builds, with the [`events`] module.
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::metadata::mpl_token_metadata;
+use anchor_spl::token;
+use counter_program::{
+    accounts, instruction, Contribution, Counter, CounterConfig, CounterError, CounterOp, History,
+    MilestoneConfig, Permit, RollingWindow,
+};
+use solana_banks_client::{BanksClient, BanksClientError};
+use solana_client::client_error::ClientError as RpcClientError;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::ed25519_program;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::system_program;
+use solana_sdk::sysvar;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+pub mod events;
+#[cfg(feature = "program-test")]
+pub mod program_test;
+
+pub use counter_program::ID as PROGRAM_ID;
+
+/// Errors returned by the client
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("counter program error: {0}")]
+    Program(CounterError),
+
+    #[error("transaction failed: {0}")]
+    Transaction(TransactionError),
+
+    #[error("banks client error: {0}")]
+    Banks(BanksClientError),
+
+    #[error("rpc error: {0}")]
+    Rpc(Box<RpcClientError>),
+
+    #[error("account not found: {0}")]
+    AccountNotFound(Pubkey),
+
+    #[error("invalid counter account: {0}")]
+    InvalidAccount(anchor_lang::error::Error),
+}
+
+impl From<TransactionError> for ClientError {
+    fn from(err: TransactionError) -> Self {
+        match counter_error(&err) {
+            Some(err) => ClientError::Program(err),
+            None => ClientError::Transaction(err),
+        }
+    }
+}
+
+impl From<BanksClientError> for ClientError {
+    fn from(err: BanksClientError) -> Self {
+        match err {
+            BanksClientError::TransactionError(err)
+            | BanksClientError::SimulationError { err, .. } => err.into(),
+            err => ClientError::Banks(err),
+        }
+    }
+}
+
+impl From<RpcClientError> for ClientError {
+    fn from(err: RpcClientError) -> Self {
+        match err.get_transaction_error() {
+            Some(err) => err.into(),
+            None => ClientError::Rpc(Box::new(err)),
+        }
+    }
+}
+
+/// Look up the `CounterError` for a custom program error code
+pub fn counter_error_from_code(code: u32) -> Option<CounterError> {
+    let discriminant = code.checked_sub(ERROR_CODE_OFFSET)?;
+    CounterError::try_from(discriminant).ok()
+}
+
+/// Extract the `CounterError` a transaction failed with, if any
+pub fn counter_error(err: &TransactionError) -> Option<CounterError> {
+    match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            counter_error_from_code(*code)
+        }
+        _ => None,
+    }
+}
+
+/// Derive the address of a counter from its creator and id
+pub fn counter_address(creator: &Pubkey, counter_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"counter", creator.as_ref(), &counter_id.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derive the address of `user`'s contribution record for a counter
+pub fn contribution_address(counter: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"contribution", counter.as_ref(), user.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derive the address of the token account a counter collects its fees in
+pub fn fee_vault_address(counter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"fee_vault", counter.as_ref()], &PROGRAM_ID)
+}
+
+/// Derive the address of a counter's history account
+pub fn history_address(counter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"history", counter.as_ref()], &PROGRAM_ID)
+}
+
+/// Derive the address of the Metaplex metadata account of `mint`
+pub fn metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", mpl_token_metadata::ID.as_ref(), mint.as_ref()],
+        &mpl_token_metadata::ID,
+    )
+}
+
+/// Derive the PDA the program signs its self-CPI events with under the
+/// `event-cpi` feature
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID).0
+}
+
+/// Build an `initialize` instruction; `creator` signs and pays for the account
+pub fn initialize(creator: &Pubkey, counter_id: u64, config: CounterConfig) -> Instruction {
+    let (counter, _) = counter_address(creator, counter_id);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::Initialize {
+            counter,
+            authority: *creator,
+            system_program: system_program::ID,
+            fee_mint: None,
+            fee_vault: None,
+            token_program: None,
+        }
+        .to_account_metas(None),
+        data: instruction::Initialize {
+            counter_id,
+            config,
+            fee: None,
+        }
+        .data(),
+    }
+}
+
+/// Build an `initialize` instruction for a counter that charges `fee` base
+/// units of `fee_mint` per increment or decrement
+pub fn initialize_with_fee(
+    creator: &Pubkey,
+    counter_id: u64,
+    config: CounterConfig,
+    fee_mint: &Pubkey,
+    fee: u64,
+) -> Instruction {
+    let (counter, _) = counter_address(creator, counter_id);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::Initialize {
+            counter,
+            authority: *creator,
+            system_program: system_program::ID,
+            fee_mint: Some(*fee_mint),
+            fee_vault: Some(fee_vault_address(&counter).0),
+            token_program: Some(token::ID),
+        }
+        .to_account_metas(None),
+        data: instruction::Initialize {
+            counter_id,
+            config,
+            fee: Some(fee),
+        }
+        .data(),
+    }
+}
+
+/// Token accounts an operator supplies to counters that charge a fee, are
+/// token-gated or pay milestone rewards
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OperatorTokens {
+    /// Account the fee is paid from
+    pub fee_source: Option<Pubkey>,
+    /// Account holding the gate mint, or an NFT of the gate collection
+    pub gate_token_account: Option<Pubkey>,
+    /// Metaplex metadata of the NFT in `gate_token_account`, for counters
+    /// gated on a collection; see [`metadata_address`]
+    pub gate_metadata: Option<Pubkey>,
+    /// Mint milestone rewards are paid in, to the operator's associated
+    /// token account
+    pub reward_mint: Option<Pubkey>,
+}
+
+/// Accounts for increments and decrements
+pub fn operate_accounts(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    tokens: OperatorTokens,
+) -> accounts::Operate {
+    let fee_source = tokens.fee_source;
+    let reward_mint = tokens.reward_mint;
+    accounts::Operate {
+        counter: *counter,
+        operator: *operator,
+        contribution: contribution_address(counter, operator).0,
+        system_program: system_program::ID,
+        fee_source,
+        fee_vault: fee_source.map(|_| fee_vault_address(counter).0),
+        token_program: fee_source.or(reward_mint).map(|_| token::ID),
+        gate_token_account: tokens.gate_token_account,
+        gate_metadata: tokens.gate_metadata,
+        reward_mint,
+        reward_account: reward_mint.map(|mint| get_associated_token_address(operator, &mint)),
+        #[cfg(feature = "event-cpi")]
+        event_authority: event_authority(),
+        #[cfg(feature = "event-cpi")]
+        program: PROGRAM_ID,
+    }
+}
+
+/// Build an `increment` instruction signed by the authority or an operator
+pub fn increment(counter: &Pubkey, operator: &Pubkey, expected: Option<u64>) -> Instruction {
+    increment_with_tokens(counter, operator, expected, OperatorTokens::default())
+}
+
+/// Build an `increment` instruction whose fee is paid from `fee_source`
+pub fn increment_with_fee(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    expected: Option<u64>,
+    fee_source: &Pubkey,
+) -> Instruction {
+    let tokens = OperatorTokens {
+        fee_source: Some(*fee_source),
+        ..OperatorTokens::default()
+    };
+    increment_with_tokens(counter, operator, expected, tokens)
+}
+
+/// Build an `increment` instruction for a counter that charges a fee or is token-gated
+pub fn increment_with_tokens(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    expected: Option<u64>,
+    tokens: OperatorTokens,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: operate_accounts(counter, operator, tokens).to_account_metas(None),
+        data: instruction::Increment { expected }.data(),
+    }
+}
+
+/// Build an Ed25519 signature check of `message` signed by `signer`, with
+/// the key, signature and message stored in the instruction itself
+pub fn ed25519_verify(signer: &Pubkey, signature: &Signature, message: &[u8]) -> Instruction {
+    // 1 (signature count) + 1 (padding) + 7 u16 offsets
+    const PUBLIC_KEY_OFFSET: u16 = 16;
+    const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        THIS_INSTRUCTION,
+        PUBLIC_KEY_OFFSET,
+        THIS_INSTRUCTION,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        THIS_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: Vec::new(),
+        data,
+    }
+}
+
+/// Build the instructions submitting `permit`, which `authority` signed
+/// off-chain as `Permit::message`. Any fee payer can send them.
+pub fn increment_with_permit(
+    authority: &Pubkey,
+    permit: &Permit,
+    signature: &Signature,
+) -> [Instruction; 2] {
+    [
+        ed25519_verify(authority, signature, &permit.message()),
+        Instruction {
+            program_id: PROGRAM_ID,
+            accounts: accounts::IncrementWithPermit {
+                counter: permit.counter,
+                instructions: sysvar::instructions::ID,
+                #[cfg(feature = "event-cpi")]
+                event_authority: event_authority(),
+                #[cfg(feature = "event-cpi")]
+                program: PROGRAM_ID,
+            }
+            .to_account_metas(None),
+            data: instruction::IncrementWithPermit {
+                nonce: permit.nonce,
+                delta: permit.delta,
+                expiry: permit.expiry,
+            }
+            .data(),
+        },
+    ]
+}
+
+/// Build a `decrement` instruction signed by the authority or an operator
+pub fn decrement(counter: &Pubkey, operator: &Pubkey, expected: Option<u64>) -> Instruction {
+    decrement_with_tokens(counter, operator, expected, OperatorTokens::default())
+}
+
+/// Build a `decrement` instruction whose fee is paid from `fee_source`
+pub fn decrement_with_fee(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    expected: Option<u64>,
+    fee_source: &Pubkey,
+) -> Instruction {
+    let tokens = OperatorTokens {
+        fee_source: Some(*fee_source),
+        ..OperatorTokens::default()
+    };
+    decrement_with_tokens(counter, operator, expected, tokens)
+}
+
+/// Build a `decrement` instruction for a counter that charges a fee or is token-gated
+pub fn decrement_with_tokens(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    expected: Option<u64>,
+    tokens: OperatorTokens,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: operate_accounts(counter, operator, tokens).to_account_metas(None),
+        data: instruction::Decrement { expected }.data(),
+    }
+}
+
+/// Build an `increment_by` instruction adding `amount`, signed by the
+/// authority or an operator
+pub fn increment_by(counter: &Pubkey, operator: &Pubkey, amount: u64) -> Instruction {
+    increment_by_with_tokens(counter, operator, amount, OperatorTokens::default())
+}
+
+/// Build an `increment_by` instruction for a counter that charges a fee, is
+/// token-gated or pays milestone rewards
+pub fn increment_by_with_tokens(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    amount: u64,
+    tokens: OperatorTokens,
+) -> Instruction {
+    program_instruction(
+        operate_accounts(counter, operator, tokens),
+        instruction::IncrementBy { amount },
+    )
+}
+
+/// Build a `decrement_by` instruction subtracting `amount`, signed by the
+/// authority or an operator
+pub fn decrement_by(counter: &Pubkey, operator: &Pubkey, amount: u64) -> Instruction {
+    decrement_by_with_tokens(counter, operator, amount, OperatorTokens::default())
+}
+
+/// Build a `decrement_by` instruction for a counter that charges a fee or is token-gated
+pub fn decrement_by_with_tokens(
+    counter: &Pubkey,
+    operator: &Pubkey,
+    amount: u64,
+    tokens: OperatorTokens,
+) -> Instruction {
+    program_instruction(
+        operate_accounts(counter, operator, tokens),
+        instruction::DecrementBy { amount },
+    )
+}
+
+/// Build a `close_contribution` instruction returning the rent of `user`'s
+/// contribution record to them
+pub fn close_contribution(counter: &Pubkey, user: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::CloseContribution {
+            contribution: contribution_address(counter, user).0,
+            counter: *counter,
+            user: *user,
+        }
+        .to_account_metas(None),
+        data: instruction::CloseContribution {}.data(),
+    }
+}
+
+/// Build a `withdraw_fees` instruction moving `amount` of the collected fees
+/// to the `destination` token account, signed by the authority
+pub fn withdraw_fees(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::WithdrawFees {
+            counter: *counter,
+            authority: *authority,
+            fee_vault: fee_vault_address(counter).0,
+            destination: *destination,
+            token_program: token::ID,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::WithdrawFees { amount }.data(),
+    }
+}
+
+/// Build a `reset` instruction signed by the authority
+pub fn reset(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(update_accounts(counter, authority), instruction::Reset {})
+}
+
+/// Build a `compare_and_set` instruction replacing the count with
+/// `new_value` if it still equals `expected`, signed by the authority
+pub fn compare_and_set(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    expected: u64,
+    new_value: u64,
+) -> Instruction {
+    program_instruction(
+        update_accounts(counter, authority),
+        instruction::CompareAndSet {
+            expected,
+            new_value,
+        },
+    )
+}
+
+/// Build an `apply_ops` instruction applying `ops` atomically, signed by the
+/// authority
+pub fn apply_ops(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    ops: Vec<CounterOp>,
+    emit_each: bool,
+) -> Instruction {
+    program_instruction(
+        update_accounts(counter, authority),
+        instruction::ApplyOps { ops, emit_each },
+    )
+}
+
+/// Build a `schedule_reset` instruction that allows the count to be reset
+/// once `delay_secs` have passed, signed by the authority
+pub fn schedule_reset(counter: &Pubkey, authority: &Pubkey, delay_secs: u32) -> Instruction {
+    program_instruction(
+        update_accounts(counter, authority),
+        instruction::ScheduleReset { delay_secs },
+    )
+}
+
+/// Build an `execute_reset` instruction carrying out a scheduled reset,
+/// signed by the authority
+pub fn execute_reset(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        update_accounts(counter, authority),
+        instruction::ExecuteReset {},
+    )
+}
+
+/// Build a `cancel_reset` instruction signed by the authority
+pub fn cancel_reset(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::CancelReset {},
+    )
+}
+
+/// Pass a counter's history account to an instruction that changes its
+/// count, as counters with a history require
+pub fn with_history(mut instruction: Instruction, counter: &Pubkey) -> Instruction {
+    instruction
+        .accounts
+        .push(AccountMeta::new(history_address(counter).0, false));
+    instruction
+}
+
+/// Build a `propose_authority` instruction starting a two-step transfer to
+/// `new_authority`, signed by the current authority
+pub fn propose_authority(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    new_authority: Pubkey,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::ProposeAuthority { new_authority },
+    )
+}
+
+/// Build an `accept_authority` instruction signed by the proposed authority
+pub fn accept_authority(counter: &Pubkey, pending_authority: &Pubkey) -> Instruction {
+    program_instruction(
+        accounts::AcceptAuthority {
+            counter: *counter,
+            pending_authority: *pending_authority,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::AcceptAuthority {},
+    )
+}
+
+/// Build a `cancel_authority_transfer` instruction signed by the current authority
+pub fn cancel_authority_transfer(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::CancelAuthorityTransfer {},
+    )
+}
+
+/// Build a `configure` instruction replacing the counter's bounds and
+/// overflow policy, signed by the authority
+pub fn configure(counter: &Pubkey, authority: &Pubkey, config: CounterConfig) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::Configure { config },
+    )
+}
+
+/// Build a `pause` instruction signed by the authority
+pub fn pause(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::Pause {},
+    )
+}
+
+/// Build an `unpause` instruction signed by the authority
+pub fn unpause(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::Unpause {},
+    )
+}
+
+/// Build an `add_operator` instruction signed by the authority
+pub fn add_operator(counter: &Pubkey, authority: &Pubkey, operator: Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::AddOperator { operator },
+    )
+}
+
+/// Build a `remove_operator` instruction signed by the authority
+pub fn remove_operator(counter: &Pubkey, authority: &Pubkey, operator: Pubkey) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::RemoveOperator { operator },
+    )
+}
+
+/// Build a `set_gate` instruction requiring operators to hold `min_balance`
+/// of `gate_mint`, or lifting the gate with `None`, signed by the authority
+pub fn set_gate(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    gate_mint: Option<Pubkey>,
+    min_balance: u64,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::SetGate {
+            gate_mint,
+            min_balance,
+        },
+    )
+}
+
+/// Build a `set_require_scheduled_reset` instruction signed by the authority
+pub fn set_require_scheduled_reset(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    required: bool,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::SetRequireScheduledReset { required },
+    )
+}
+
+/// Build a `set_collection_gate` instruction requiring operators to hold an
+/// NFT of the verified `collection`, or lifting the gate with `None`, signed
+/// by the authority
+pub fn set_collection_gate(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    collection: Option<Pubkey>,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::SetCollectionGate { collection },
+    )
+}
+
+/// Build a `set_cooldown` instruction signed by the authority
+pub fn set_cooldown(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    cooldown_slots: u64,
+    caller_cooldown_slots: u64,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::SetCooldown {
+            cooldown_slots,
+            caller_cooldown_slots,
+        },
+    )
+}
+
+/// Build a `set_window` instruction signed by the authority
+pub fn set_window(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    window: Option<RollingWindow>,
+) -> Instruction {
+    program_instruction(
+        authority_accounts(counter, authority),
+        instruction::SetWindow { window },
+    )
+}
+
+/// Build a `set_milestones` instruction paying rewards in `reward_mint`,
+/// which the counter must be the mint authority of, signed by the authority
+pub fn set_milestones(
+    counter: &Pubkey,
+    authority: &Pubkey,
+    reward_mint: Option<Pubkey>,
+    milestones: Vec<MilestoneConfig>,
+) -> Instruction {
+    program_instruction(
+        accounts::SetMilestones {
+            counter: *counter,
+            authority: *authority,
+            reward_mint,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::SetMilestones { milestones },
+    )
+}
+
+/// Build an `init_history` instruction creating the counter's history
+/// account, paid for by the authority
+pub fn init_history(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        accounts::InitHistory {
+            counter: *counter,
+            history: history_address(counter).0,
+            authority: *authority,
+            system_program: system_program::ID,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::InitHistory {},
+    )
+}
+
+/// Build a `close_history` instruction returning the history account's rent
+/// to the authority
+pub fn close_history(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        accounts::CloseHistory {
+            counter: *counter,
+            history: history_address(counter).0,
+            authority: *authority,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::CloseHistory {},
+    )
+}
+
+/// Build a `close` instruction returning the counter's rent to the authority
+pub fn close(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        close_accounts(counter, authority, false),
+        instruction::Close {},
+    )
+}
+
+/// Build a `close` instruction for a counter that charges a fee, closing
+/// its vault along with it
+pub fn close_with_fee(counter: &Pubkey, authority: &Pubkey) -> Instruction {
+    program_instruction(
+        close_accounts(counter, authority, true),
+        instruction::Close {},
+    )
+}
+
+/// Build a `migrate` instruction upgrading a counter to the current
+/// layout; `payer` covers any extra rent
+pub fn migrate(counter: &Pubkey, payer: &Pubkey) -> Instruction {
+    program_instruction(
+        accounts::Migrate {
+            counter: *counter,
+            payer: *payer,
+            system_program: system_program::ID,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::Migrate {},
+    )
+}
+
+/// Build a `migrate_counter` instruction moving a counter from its legacy
+/// address to `counter_address(creator, counter_id)`, signed by its authority
+pub fn migrate_counter(
+    legacy_counter: &Pubkey,
+    authority: &Pubkey,
+    creator: Pubkey,
+    counter_id: u64,
+) -> Instruction {
+    program_instruction(
+        accounts::MigrateCounter {
+            legacy_counter: *legacy_counter,
+            counter: counter_address(&creator, counter_id).0,
+            authority: *authority,
+            system_program: system_program::ID,
+            #[cfg(feature = "event-cpi")]
+            event_authority: event_authority(),
+            #[cfg(feature = "event-cpi")]
+            program: PROGRAM_ID,
+        },
+        instruction::MigrateCounter {
+            creator,
+            counter_id,
+        },
+    )
+}
+
+/// Build a counter program instruction from Anchor's generated accounts and data
+fn program_instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Accounts for the authority's count updates, which may change the count
+fn update_accounts(counter: &Pubkey, authority: &Pubkey) -> accounts::Update {
+    accounts::Update {
+        counter: *counter,
+        authority: *authority,
+        #[cfg(feature = "event-cpi")]
+        event_authority: event_authority(),
+        #[cfg(feature = "event-cpi")]
+        program: PROGRAM_ID,
+    }
+}
+
+/// Accounts for the authority's settings changes
+fn authority_accounts(counter: &Pubkey, authority: &Pubkey) -> accounts::UpdateAuthority {
+    accounts::UpdateAuthority {
+        counter: *counter,
+        authority: *authority,
+        #[cfg(feature = "event-cpi")]
+        event_authority: event_authority(),
+        #[cfg(feature = "event-cpi")]
+        program: PROGRAM_ID,
+    }
+}
+
+/// Accounts for `close`, with the fee vault for counters that charge a fee
+fn close_accounts(counter: &Pubkey, authority: &Pubkey, with_fee: bool) -> accounts::CloseCounter {
+    accounts::CloseCounter {
+        counter: *counter,
+        authority: *authority,
+        fee_vault: with_fee.then(|| fee_vault_address(counter).0),
+        token_program: with_fee.then_some(token::ID),
+        #[cfg(feature = "event-cpi")]
+        event_authority: event_authority(),
+        #[cfg(feature = "event-cpi")]
+        program: PROGRAM_ID,
+    }
+}
+
+/// Decode raw account data into a `Counter`
+pub fn decode_counter(data: &[u8]) -> Result<Counter, ClientError> {
+    Counter::try_deserialize(&mut &data[..]).map_err(ClientError::InvalidAccount)
+}
+
+/// Fetch and decode a counter through `solana-program-test`'s banks client
+pub async fn fetch_counter(
+    banks_client: &mut BanksClient,
+    address: &Pubkey,
+) -> Result<Counter, ClientError> {
+    let account = banks_client
+        .get_account(*address)
+        .await?
+        .ok_or(ClientError::AccountNotFound(*address))?;
+    decode_counter(&account.data)
+}
+
+/// Fetch and decode a counter from an RPC node
+pub fn fetch_counter_rpc(rpc_client: &RpcClient, address: &Pubkey) -> Result<Counter, ClientError> {
+    let account = rpc_client.get_account(address)?;
+    decode_counter(&account.data)
+}
+
+/// Decode raw account data into a `Contribution`
+pub fn decode_contribution(data: &[u8]) -> Result<Contribution, ClientError> {
+    Contribution::try_deserialize(&mut &data[..]).map_err(ClientError::InvalidAccount)
+}
+
+/// Fetch and decode a contribution record through `solana-program-test`'s banks client
+pub async fn fetch_contribution(
+    banks_client: &mut BanksClient,
+    address: &Pubkey,
+) -> Result<Contribution, ClientError> {
+    let account = banks_client
+        .get_account(*address)
+        .await?
+        .ok_or(ClientError::AccountNotFound(*address))?;
+    decode_contribution(&account.data)
+}
+
+/// Fetch and decode a contribution record from an RPC node
+pub fn fetch_contribution_rpc(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+) -> Result<Contribution, ClientError> {
+    let account = rpc_client.get_account(address)?;
+    decode_contribution(&account.data)
+}
+
+/// Decode raw account data into a `History`
+pub fn decode_history(data: &[u8]) -> Result<History, ClientError> {
+    History::try_deserialize(&mut &data[..]).map_err(ClientError::InvalidAccount)
+}
+
+/// Fetch and decode a history account through `solana-program-test`'s banks client
+pub async fn fetch_history(
+    banks_client: &mut BanksClient,
+    address: &Pubkey,
+) -> Result<History, ClientError> {
+    let account = banks_client
+        .get_account(*address)
+        .await?
+        .ok_or(ClientError::AccountNotFound(*address))?;
+    decode_history(&account.data)
+}
+
+/// Fetch and decode a history account from an RPC node
+pub fn fetch_history_rpc(rpc_client: &RpcClient, address: &Pubkey) -> Result<History, ClientError> {
+    let account = rpc_client.get_account(address)?;
+    decode_history(&account.data)
+}
+
+/// Process a signed transaction through the banks client, mapping failures
+/// to `ClientError::Program` where the counter program rejected it
+pub async fn process_transaction(
+    banks_client: &mut BanksClient,
+    transaction: Transaction,
+) -> Result<(), ClientError> {
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(Into::into)
+}
+
+/// Send and confirm a signed transaction over RPC, mapping failures to
+/// `ClientError::Program` where the counter program rejected it
+pub fn send_transaction_rpc(
+    rpc_client: &RpcClient,
+    transaction: &Transaction,
+) -> Result<Signature, ClientError> {
+    rpc_client
+        .send_and_confirm_transaction(transaction)
+        .map_err(Into::into)
+}
@@ -0,0 +1,196 @@
+//! Helpers for running the counter program natively under `solana-program-test`.
+//!
+//! The native runtime drops `sol_log_data`, which `emit!` relies on, so
+//! [`start`] installs a syscall shim that writes event data to the regular
+//! program log instead. [`process_transaction`] turns those lines back into
+//! the `Program data:` lines a validator produces, so logs can be fed
+//! straight into [`crate::events`].
+//!
+//! The banks client does not report inner instructions either, so the shim
+//! also records every CPI the program makes. [`process_transaction`]
+//! returns them, letting `event-cpi` builds be checked with
+//! [`crate::events::parse_inner_instructions`]. Recording relies on the
+//! transaction being processed on the calling thread, as it is under
+//! `#[tokio::test]`'s current-thread runtime.
+
+use std::cell::RefCell;
+use std::sync::Once;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::{ClientError, PROGRAM_ID};
+
+/// Marker the shim puts in front of event data written to the program log
+const DATA_MARKER: &str = "\u{1}data: ";
+
+thread_local! {
+    /// CPIs made by the program while processing the current transaction
+    static INNER_INSTRUCTIONS: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Result of a transaction processed through [`process_transaction`]
+#[derive(Debug)]
+pub struct TransactionOutcome {
+    pub result: Result<(), ClientError>,
+    pub logs: Vec<String>,
+    pub compute_units_consumed: u64,
+    /// CPIs made by the counter program, in the order they were invoked
+    pub inner_instructions: Vec<Instruction>,
+}
+
+/// A `ProgramTest` with the counter program registered as a native program
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "counter_program",
+        PROGRAM_ID,
+        processor!(process_instruction),
+    )
+}
+
+/// Start `program_test` and install the event log shim
+pub async fn start(program_test: ProgramTest) -> ProgramTestContext {
+    let context = program_test.start_with_context().await;
+
+    // `solana-program-test` installs its own stubs on first start, so the
+    // shim has to wrap them afterwards
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let inner = set_syscall_stubs(Box::new(Placeholder));
+        set_syscall_stubs(Box::new(LogDataStubs(inner)));
+    });
+
+    context
+}
+
+/// Process a signed transaction and collect its logs and compute units
+pub async fn process_transaction(
+    banks_client: &mut BanksClient,
+    transaction: Transaction,
+) -> Result<TransactionOutcome, ClientError> {
+    INNER_INSTRUCTIONS.with(|recorded| recorded.borrow_mut().clear());
+    let processed = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await?;
+    let inner_instructions = INNER_INSTRUCTIONS.with(|recorded| recorded.take());
+    let (logs, compute_units_consumed) = processed
+        .metadata
+        .map(|metadata| (metadata.log_messages, metadata.compute_units_consumed))
+        .unwrap_or_default();
+
+    let logs = logs
+        .into_iter()
+        .map(|line| match line.strip_prefix("Program log: ") {
+            Some(message) if message.starts_with(DATA_MARKER) => {
+                format!("Program data: {}", &message[DATA_MARKER.len()..])
+            }
+            _ => line,
+        })
+        .collect();
+
+    Ok(TransactionOutcome {
+        result: processed.result.map_err(Into::into),
+        logs,
+        compute_units_consumed,
+        inner_instructions,
+    })
+}
+
+fn process_instruction<'a, 'info>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+    data: &[u8],
+) -> ProgramResult {
+    // SAFETY: Anchor's entry point ties the slice to the accounts' lifetime.
+    // `'info` outlives `'a`, and shortening it to `'a` only narrows what the
+    // program may keep: nothing it is handed outlives this call, so no
+    // reference it stores can outlive the accounts it points into.
+    let accounts =
+        unsafe { std::mem::transmute::<&'a [AccountInfo<'info>], &'a [AccountInfo<'a>]>(accounts) };
+    counter_program::entry(program_id, accounts, data)
+}
+
+struct Placeholder;
+
+impl SyscallStubs for Placeholder {}
+
+/// Delegates every syscall to `solana-program-test`'s stubs, except
+/// `sol_log_data`, which is written to the program log behind [`DATA_MARKER`],
+/// and `sol_invoke_signed`, which is also recorded in [`INNER_INSTRUCTIONS`]
+struct LogDataStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INNER_INSTRUCTIONS.with(|recorded| recorded.borrow_mut().push(instruction.clone()));
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.0.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.0.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        self.0
+            .sol_log(&format!("{DATA_MARKER}{}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
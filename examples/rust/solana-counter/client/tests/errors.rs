@@ -0,0 +1,34 @@
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use counter_client::{counter_error, counter_error_from_code};
+use counter_program::CounterError;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::TransactionError;
+
+#[test]
+fn every_error_code_round_trips() {
+    // Discriminants run from 0 without gaps, so walking them until the first
+    // miss visits every variant
+    let variants: Vec<CounterError> = (0..)
+        .map_while(|discriminant| CounterError::try_from(discriminant).ok())
+        .collect();
+    assert!(variants.len() > 1);
+
+    for (discriminant, variant) in (0..).zip(&variants) {
+        let code = u32::from(*variant);
+        assert_eq!(code, ERROR_CODE_OFFSET + discriminant);
+
+        let decoded = counter_error_from_code(code).unwrap();
+        assert_eq!(decoded.name(), variant.name());
+
+        let err = TransactionError::InstructionError(0, InstructionError::Custom(code));
+        assert_eq!(counter_error(&err).unwrap().name(), variant.name());
+    }
+
+    let names: std::collections::HashSet<_> = variants.iter().map(CounterError::name).collect();
+    assert_eq!(names.len(), variants.len());
+
+    let past_the_end = ERROR_CODE_OFFSET + variants.len() as u32;
+    assert!(counter_error_from_code(past_the_end).is_none());
+    assert!(counter_error_from_code(ERROR_CODE_OFFSET - 1).is_none());
+    assert!(counter_error_from_code(0).is_none());
+}
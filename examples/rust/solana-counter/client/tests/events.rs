@@ -0,0 +1,424 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::Event;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+#[cfg(not(feature = "event-cpi"))]
+use counter_client::events::decode_logs;
+use counter_client::events::{
+    decode_transaction, parse_inner_instructions, parse_logs, CounterEvent,
+};
+use counter_client::program_test::{process_transaction, program_test, start, TransactionOutcome};
+#[cfg(feature = "event-cpi")]
+use counter_client::{apply_ops, set_window};
+use counter_client::{
+    counter_address, decrement, event_authority, increment, initialize, propose_authority, reset,
+    PROGRAM_ID,
+};
+use counter_program::{CounterConfig, CounterReset, CounterUpdated};
+#[cfg(feature = "event-cpi")]
+use counter_program::{CounterOp, RollingWindow};
+use serde_json::json;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::bs58;
+#[cfg(feature = "event-cpi")]
+use solana_sdk::clock::Clock;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+/// Send `instruction` signed by the payer and return its signature, slot and outcome
+async fn send(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+) -> (Signature, u64, TransactionOutcome) {
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        blockhash,
+    );
+    let signature = transaction.signatures[0];
+
+    let outcome = process_transaction(&mut context.banks_client, transaction)
+        .await
+        .unwrap();
+    outcome.result.as_ref().unwrap();
+
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    (signature, slot, outcome)
+}
+
+/// Decode the events among the CPIs recorded for a transaction
+#[cfg(feature = "event-cpi")]
+fn inner_events(outcome: &TransactionOutcome) -> Vec<CounterEvent> {
+    parse_inner_instructions(
+        outcome
+            .inner_instructions
+            .iter()
+            .map(|instruction| (&instruction.program_id, instruction.data.as_slice())),
+    )
+}
+
+#[cfg(not(feature = "event-cpi"))]
+#[tokio::test]
+async fn decodes_events_from_program_test_logs() {
+    let mut context = start(program_test()).await;
+    let payer = context.payer.pubkey();
+    let (counter, _) = counter_address(&payer, 0);
+    let new_authority = Pubkey::new_unique();
+
+    send(
+        &mut context,
+        initialize(&payer, 0, CounterConfig::default()),
+    )
+    .await;
+
+    let (signature, slot, outcome) = send(&mut context, increment(&counter, &payer, None)).await;
+    let events = decode_logs(slot, signature, &outcome.logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].signature, signature);
+    assert_eq!(events[0].slot, slot);
+    let CounterEvent::Updated(updated) = &events[0].event else {
+        panic!("expected CounterUpdated, got {:?}", events[0].event);
+    };
+    assert_eq!(updated.counter, counter);
+    assert_eq!(updated.previous_value, 0);
+    assert_eq!(updated.new_value, 1);
+    assert_eq!(updated.delta, 1);
+
+    let (_, _, outcome) = send(&mut context, decrement(&counter, &payer, Some(1))).await;
+    match parse_logs(&outcome.logs).as_slice() {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!(updated.previous_value, 1);
+            assert_eq!(updated.new_value, 0);
+            assert_eq!(updated.delta, -1);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let (_, _, outcome) = send(&mut context, reset(&counter, &payer)).await;
+    match parse_logs(&outcome.logs).as_slice() {
+        [CounterEvent::Reset(reset)] => assert_eq!(reset.counter, counter),
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let (_, _, outcome) = send(
+        &mut context,
+        propose_authority(&counter, &payer, new_authority),
+    )
+    .await;
+    match parse_logs(&outcome.logs).as_slice() {
+        [CounterEvent::AuthorityProposed(proposed)] => {
+            assert_eq!(proposed.authority, payer);
+            assert_eq!(proposed.pending_authority, new_authority);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+}
+
+#[cfg(feature = "event-cpi")]
+#[tokio::test]
+async fn decodes_events_from_program_test_inner_instructions() {
+    let mut context = start(program_test()).await;
+    let payer = context.payer.pubkey();
+    let (counter, _) = counter_address(&payer, 0);
+    let new_authority = Pubkey::new_unique();
+
+    send(
+        &mut context,
+        initialize(&payer, 0, CounterConfig::default()),
+    )
+    .await;
+
+    // Self-CPI events never reach the program log, so they are recovered
+    // from the inner instructions alone
+    let (_, _, outcome) = send(&mut context, increment(&counter, &payer, None)).await;
+    assert!(parse_logs(&outcome.logs).is_empty());
+    match inner_events(&outcome).as_slice() {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!(updated.counter, counter);
+            assert_eq!(updated.previous_value, 0);
+            assert_eq!(updated.new_value, 1);
+            assert_eq!(updated.delta, 1);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let (_, _, outcome) = send(&mut context, decrement(&counter, &payer, Some(1))).await;
+    match inner_events(&outcome).as_slice() {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!(updated.previous_value, 1);
+            assert_eq!(updated.new_value, 0);
+            assert_eq!(updated.delta, -1);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let (_, _, outcome) = send(&mut context, reset(&counter, &payer)).await;
+    match inner_events(&outcome).as_slice() {
+        [CounterEvent::Reset(reset)] => assert_eq!(reset.counter, counter),
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let (_, _, outcome) = send(
+        &mut context,
+        propose_authority(&counter, &payer, new_authority),
+    )
+    .await;
+    match inner_events(&outcome).as_slice() {
+        [CounterEvent::AuthorityProposed(proposed)] => {
+            assert_eq!(proposed.authority, payer);
+            assert_eq!(proposed.pending_authority, new_authority);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+}
+
+#[test]
+fn decodes_inner_instruction_events_when_logs_are_truncated() {
+    let payer = Pubkey::new_unique();
+    let counter = Pubkey::new_unique();
+    let other_program = Pubkey::new_unique();
+    let signature = Signature::new_unique();
+    let event = [
+        &EVENT_IX_TAG_LE[..],
+        &CounterUpdated {
+            counter,
+            previous_value: 4,
+            new_value: 5,
+            delta: 1,
+            timestamp: 0,
+        }
+        .data(),
+    ]
+    .concat();
+
+    let transaction: EncodedConfirmedTransactionWithStatusMeta = serde_json::from_value(json!({
+        "slot": 42,
+        "transaction": {
+            "signatures": [signature.to_string()],
+            "message": {
+                "header": {
+                    "numRequiredSignatures": 1,
+                    "numReadonlySignedAccounts": 0,
+                    "numReadonlyUnsignedAccounts": 3,
+                },
+                "accountKeys": [
+                    payer.to_string(),
+                    counter.to_string(),
+                    event_authority().to_string(),
+                    PROGRAM_ID.to_string(),
+                    other_program.to_string(),
+                ],
+                "recentBlockhash": Hash::default().to_string(),
+                "instructions": [{
+                    "programIdIndex": 3,
+                    "accounts": [1, 0, 2, 3],
+                    "data": bs58::encode(increment(&counter, &payer, None).data).into_string(),
+                }],
+            },
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [0, 0, 0, 0, 0],
+            "postBalances": [0, 0, 0, 0, 0],
+            "innerInstructions": [{
+                "index": 0,
+                "instructions": [
+                    {
+                        "programIdIndex": 4,
+                        "accounts": [],
+                        "data": bs58::encode(&event).into_string(),
+                        "stackHeight": 2,
+                    },
+                    {
+                        "programIdIndex": 3,
+                        "accounts": [2],
+                        "data": bs58::encode(&event).into_string(),
+                        "stackHeight": 2,
+                    },
+                ],
+            }],
+            "logMessages": [format!("Program {PROGRAM_ID} invoke [1]"), "Log truncated"],
+        },
+    }))
+    .unwrap();
+
+    let events = decode_transaction(&transaction);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].slot, 42);
+    assert_eq!(events[0].signature, signature);
+    let CounterEvent::Updated(updated) = &events[0].event else {
+        panic!("expected CounterUpdated, got {:?}", events[0].event);
+    };
+    assert_eq!(updated.counter, counter);
+    assert_eq!(updated.new_value, 5);
+}
+
+#[cfg(feature = "event-cpi")]
+#[tokio::test]
+async fn decodes_recorded_inner_instructions_when_logs_are_truncated() {
+    let mut context = start(program_test()).await;
+    let payer = context.payer.pubkey();
+    let (counter, _) = counter_address(&payer, 0);
+
+    send(
+        &mut context,
+        initialize(&payer, 0, CounterConfig::default()),
+    )
+    .await;
+    send(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Seconds(60))),
+    )
+    .await;
+    send(&mut context, increment(&counter, &payer, None)).await;
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += 60;
+    context.set_sysvar(&clock);
+
+    // A batch rolling the window over emits three kinds of event
+    let ops = vec![CounterOp::IncrementBy(3), CounterOp::Decrement];
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[apply_ops(&counter, &payer, ops, true)],
+        Some(&payer),
+        &[&context.payer],
+        blockhash,
+    );
+    let message = transaction.message.clone();
+    let outcome = process_transaction(&mut context.banks_client, transaction.clone())
+        .await
+        .unwrap();
+    outcome.result.as_ref().unwrap();
+
+    let index = |key: &Pubkey| {
+        message
+            .account_keys
+            .iter()
+            .position(|account| account == key)
+            .unwrap()
+    };
+    let inner_instructions: Vec<_> = outcome
+        .inner_instructions
+        .iter()
+        .map(|instruction| {
+            json!({
+                "programIdIndex": index(&instruction.program_id),
+                "accounts": instruction
+                    .accounts
+                    .iter()
+                    .map(|meta| index(&meta.pubkey))
+                    .collect::<Vec<_>>(),
+                "data": bs58::encode(&instruction.data).into_string(),
+                "stackHeight": 2,
+            })
+        })
+        .collect();
+    let balances = vec![0; message.account_keys.len()];
+    let encoded: EncodedConfirmedTransactionWithStatusMeta = serde_json::from_value(json!({
+        "slot": 42,
+        "transaction": {
+            "signatures": [transaction.signatures[0].to_string()],
+            "message": {
+                "header": {
+                    "numRequiredSignatures": message.header.num_required_signatures,
+                    "numReadonlySignedAccounts": message.header.num_readonly_signed_accounts,
+                    "numReadonlyUnsignedAccounts": message.header.num_readonly_unsigned_accounts,
+                },
+                "accountKeys": message
+                    .account_keys
+                    .iter()
+                    .map(Pubkey::to_string)
+                    .collect::<Vec<_>>(),
+                "recentBlockhash": message.recent_blockhash.to_string(),
+                "instructions": [{
+                    "programIdIndex": message.instructions[0].program_id_index,
+                    "accounts": message.instructions[0].accounts,
+                    "data": bs58::encode(&message.instructions[0].data).into_string(),
+                }],
+            },
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": balances,
+            "postBalances": balances,
+            "innerInstructions": [{ "index": 0, "instructions": inner_instructions }],
+            "logMessages": [format!("Program {PROGRAM_ID} invoke [1]"), "Log truncated"],
+        },
+    }))
+    .unwrap();
+
+    let events = decode_transaction(&encoded);
+    assert!(events
+        .iter()
+        .all(|event| event.slot == 42 && event.signature == transaction.signatures[0]));
+    let events: Vec<_> = events.into_iter().map(|event| event.event).collect();
+    match events.as_slice() {
+        [CounterEvent::WindowClosed(closed), CounterEvent::Updated(first), CounterEvent::Updated(second), CounterEvent::OpsApplied(applied)] =>
+        {
+            assert_eq!(closed.counter, counter);
+            assert_eq!(closed.total, 1);
+            assert_eq!((first.previous_value, first.new_value), (0, 3));
+            assert_eq!((second.previous_value, second.new_value), (3, 2));
+            assert_eq!((applied.previous_value, applied.new_value), (0, 2));
+            assert_eq!(applied.ops, 2);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+}
+
+#[test]
+fn ignores_data_logged_by_other_programs() {
+    let data = CounterReset {
+        counter: Pubkey::new_unique(),
+        timestamp: 0,
+    }
+    .data();
+    let other_program = Pubkey::new_unique();
+
+    let logs = vec![
+        format!("Program {PROGRAM_ID} invoke [1]"),
+        format!("Program {other_program} invoke [2]"),
+        format!("Program data: {}", STANDARD.encode(&data)),
+        format!("Program {other_program} success"),
+        "Program data: not-base64".to_string(),
+        format!("Program data: {}", STANDARD.encode([0u8; 16])),
+        format!("Program data: {}", STANDARD.encode(&data)),
+        format!("Program {PROGRAM_ID} success"),
+    ];
+
+    let events = parse_logs(&logs);
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], CounterEvent::Reset(_)));
+}
+
+#[test]
+fn ignores_inner_instructions_of_other_programs() {
+    let data = CounterReset {
+        counter: Pubkey::new_unique(),
+        timestamp: 0,
+    }
+    .data();
+    let event = [&EVENT_IX_TAG_LE[..], &data].concat();
+    let other_program = Pubkey::new_unique();
+
+    let instructions = [
+        (&other_program, event.as_slice()),
+        (&PROGRAM_ID, data.as_slice()),
+        (&PROGRAM_ID, &event[..12]),
+        (&PROGRAM_ID, event.as_slice()),
+    ];
+
+    let events = parse_inner_instructions(instructions);
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], CounterEvent::Reset(_)));
+}
@@ -1,198 +1,3109 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::metadata::MetadataAccount;
+use anchor_spl::token::{self, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer};
+use num_enum::TryFromPrimitive;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
+// Emit a counter event through the program log (`emit!`), or through a
+// self-CPI (`emit_cpi!`) when built with the `event-cpi` feature. Self-CPI
+// events land in the transaction's inner instructions, which RPC nodes keep
+// even when they truncate or drop program logs.
+#[cfg(not(feature = "event-cpi"))]
+macro_rules! emit_event {
+    ($ctx:ident, $event:expr) => {
+        emit!($event)
+    };
+}
+
+#[cfg(feature = "event-cpi")]
+macro_rules! emit_event {
+    ($ctx:ident, $event:expr) => {{
+        // Build the event first so borrows of the accounts it reads end
+        // before `emit_cpi!` reads the event authority from `ctx`
+        let event = $event;
+        let ctx = &$ctx;
+        emit_cpi!(event)
+    }};
+}
+
 /// Counter program demonstrating Solana/Anchor development
 #[program]
 pub mod counter_program {
     use super::*;
 
-    /// Initialize a new counter account
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// Initialize a new counter account, starting at the configured minimum.
+    ///
+    /// `counter_id` lets one wallet create any number of independent counters.
+    /// With `fee` set, every increment and decrement costs that many base
+    /// units of `fee_mint`, collected in a vault owned by the counter.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        counter_id: u64,
+        config: CounterConfig,
+        fee: Option<u64>,
+    ) -> Result<()> {
+        let fee = match (fee, &ctx.accounts.fee_mint, &ctx.accounts.fee_vault) {
+            (Some(amount), Some(mint), Some(vault)) => Some(Fee {
+                mint: mint.key(),
+                vault: vault.key(),
+                amount,
+            }),
+            (None, None, None) => None,
+            _ => return err!(CounterError::InvalidFeeAccounts),
+        };
+
         let counter = &mut ctx.accounts.counter;
-        counter.count = 0;
+        counter.count = config.min;
         counter.authority = ctx.accounts.authority.key();
         counter.bump = ctx.bumps.counter;
+        counter.creator = ctx.accounts.authority.key();
+        counter.counter_id = counter_id;
+        counter.fee = fee;
+        counter.version = Counter::VERSION;
+        counter.apply_config(&config)?;
+
+        msg!(
+            "Counter {} initialized with authority: {}",
+            counter_id,
+            counter.authority
+        );
+        Ok(())
+    }
+
+    /// Increment the counter by 1, optionally only if it still equals `expected`
+    pub fn increment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Operate<'info>>,
+        expected: Option<u64>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.counter.check_expected(expected, &clock)?;
+        increment_by(ctx, 1)
+    }
+
+    /// Decrement the counter by 1, optionally only if it still equals `expected`
+    pub fn decrement<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Operate<'info>>,
+        expected: Option<u64>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.counter.check_expected(expected, &clock)?;
+        decrement_by(ctx, 1)
+    }
+
+    /// Increment the counter by an arbitrary amount
+    pub fn increment_by<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Operate<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.check_cooldown()?;
+        ctx.accounts.check_gate()?;
+        ctx.accounts.charge_fee()?;
+        ctx.accounts
+            .record_contribution(ctx.bumps.contribution, true)?;
+
+        let clock = Clock::get()?;
+        let operator = ctx.accounts.operator.key();
+        let mut entries = Vec::with_capacity(2);
+        let rolled = ctx.accounts.counter.roll_window(&clock);
+        if let Some((_, total)) = rolled {
+            entries.push(HistoryEntry::new(
+                HistoryKind::Rollover,
+                total,
+                ctx.accounts.counter.count,
+                operator,
+                clock.slot,
+            ));
+        }
+
+        let counter = &mut ctx.accounts.counter;
+        let previous_value = counter.count;
+
+        counter.apply_delta(i128::from(amount))?;
+        counter.last_slot = clock.slot;
+        entries.push(HistoryEntry::new(
+            HistoryKind::Increment,
+            previous_value,
+            counter.count,
+            operator,
+            clock.slot,
+        ));
+        record_history(counter, ctx.remaining_accounts, &entries)?;
+
+        let reached = ctx.accounts.counter.reach_milestones(previous_value);
+        ctx.accounts.mint_rewards(&reached)?;
+
+        // Nothing can fail from here on, so a rejected update emits nothing
+        let new_value = ctx.accounts.counter.count;
+        msg!("Counter incremented by {} to: {}", amount, new_value);
+
+        if let Some((window_index, total)) = rolled {
+            emit_event!(
+                ctx,
+                WindowClosed {
+                    counter: ctx.accounts.counter.key(),
+                    window_index,
+                    total,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+        emit_event!(
+            ctx,
+            CounterUpdated {
+                counter: ctx.accounts.counter.key(),
+                previous_value,
+                new_value,
+                delta: i128::from(amount),
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        for milestone in reached {
+            msg!("Milestone {} reached", milestone.threshold);
+
+            emit_event!(
+                ctx,
+                MilestoneReached {
+                    counter: ctx.accounts.counter.key(),
+                    recipient: ctx.accounts.operator.key(),
+                    threshold: milestone.threshold,
+                    reward: milestone.reward,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Decrement the counter by an arbitrary amount
+    pub fn decrement_by<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Operate<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.check_cooldown()?;
+        ctx.accounts.check_gate()?;
+        ctx.accounts.charge_fee()?;
+        ctx.accounts
+            .record_contribution(ctx.bumps.contribution, false)?;
+
+        let clock = Clock::get()?;
+        let operator = ctx.accounts.operator.key();
+        let mut entries = Vec::with_capacity(2);
+        let rolled = ctx.accounts.counter.roll_window(&clock);
+        if let Some((_, total)) = rolled {
+            entries.push(HistoryEntry::new(
+                HistoryKind::Rollover,
+                total,
+                ctx.accounts.counter.count,
+                operator,
+                clock.slot,
+            ));
+        }
+
+        let counter = &mut ctx.accounts.counter;
+        let previous_value = counter.count;
+
+        counter.apply_delta(-i128::from(amount))?;
+        counter.last_slot = clock.slot;
+        entries.push(HistoryEntry::new(
+            HistoryKind::Decrement,
+            previous_value,
+            counter.count,
+            operator,
+            clock.slot,
+        ));
+        record_history(counter, ctx.remaining_accounts, &entries)?;
+
+        msg!("Counter decremented by {} to: {}", amount, counter.count);
+
+        if let Some((window_index, total)) = rolled {
+            emit_event!(
+                ctx,
+                WindowClosed {
+                    counter: ctx.accounts.counter.key(),
+                    window_index,
+                    total,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+        let counter = &ctx.accounts.counter;
+        emit_event!(
+            ctx,
+            CounterUpdated {
+                counter: counter.key(),
+                previous_value,
+                new_value: counter.count,
+                delta: -i128::from(amount),
+                timestamp: clock.unix_timestamp,
+            }
+        );
 
-        msg!("Counter initialized with authority: {}", counter.authority);
         Ok(())
     }
 
-    /// Increment the counter by 1
-    pub fn increment(ctx: Context<Update>) -> Result<()> {
+    /// Increment the counter by `delta` on the strength of a permit the
+    /// authority signed off-chain, so any fee payer can submit it.
+    ///
+    /// The instruction right before this one must be an Ed25519 signature
+    /// check of `Permit::message` by the authority. `nonce` must equal the
+    /// counter's `permit_nonce`, which then moves on, so every permit is used
+    /// at most once.
+    ///
+    /// Only the counter-wide cooldown applies. The permit's signer takes no
+    /// part in the transaction and has no contribution record, so the
+    /// per-caller cooldown does not: the authority meters permits through
+    /// their nonces and deltas instead.
+    pub fn increment_with_permit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, IncrementWithPermit<'info>>,
+        nonce: u64,
+        delta: u64,
+        expiry: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(clock.unix_timestamp <= expiry, CounterError::PermitExpired);
+
         let counter = &mut ctx.accounts.counter;
+        require!(
+            nonce == counter.permit_nonce,
+            CounterError::InvalidPermitNonce
+        );
+
+        let permit = Permit {
+            counter: counter.key(),
+            nonce,
+            delta,
+            expiry,
+        };
+        verify_ed25519(
+            &ctx.accounts.instructions,
+            &counter.authority,
+            &permit.message(),
+        )?;
+        // No contribution record to take a caller cooldown from
+        counter.check_cooldown(0)?;
+
+        let authority = counter.authority;
+        let mut entries = Vec::with_capacity(2);
+        let rolled = counter.roll_window(&clock);
+        if let Some((_, total)) = rolled {
+            entries.push(HistoryEntry::new(
+                HistoryKind::Rollover,
+                total,
+                counter.count,
+                authority,
+                clock.slot,
+            ));
+        }
+
+        counter.permit_nonce = nonce.checked_add(1).ok_or(CounterError::Overflow)?;
+        let previous_value = counter.count;
+        counter.apply_delta(i128::from(delta))?;
+        counter.last_slot = clock.slot;
+        counter.pass_milestones(previous_value);
+        entries.push(HistoryEntry::new(
+            HistoryKind::Increment,
+            previous_value,
+            counter.count,
+            authority,
+            clock.slot,
+        ));
+        record_history(counter, ctx.remaining_accounts, &entries)?;
 
+        msg!(
+            "Counter incremented by {} to: {} with permit {}",
+            delta,
+            counter.count,
+            nonce
+        );
+
+        if let Some((window_index, total)) = rolled {
+            emit_event!(
+                ctx,
+                WindowClosed {
+                    counter: ctx.accounts.counter.key(),
+                    window_index,
+                    total,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+        let counter = &ctx.accounts.counter;
+        emit_event!(
+            ctx,
+            CounterUpdated {
+                counter: counter.key(),
+                previous_value,
+                new_value: counter.count,
+                delta: i128::from(delta),
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Set the counter to `new_value` only if it still equals `expected`,
+    /// after rolling the window over if a new one has started. Rejected
+    /// while the counter requires scheduled resets.
+    pub fn compare_and_set<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Update<'info>>,
+        expected: u64,
+        new_value: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let authority = ctx.accounts.authority.key();
+        let counter = &ctx.accounts.counter;
+        counter.check_instant_reset()?;
+        counter.check_expected(Some(expected), &clock)?;
         require!(
-            counter.count < u64::MAX,
-            CounterError::Overflow
+            (counter.min..=counter.max).contains(&new_value),
+            CounterError::OutOfBounds
         );
 
-        counter.count = counter.count.checked_add(1)
-            .ok_or(CounterError::Overflow)?;
+        let mut entries = Vec::with_capacity(2);
+        let rolled = ctx.accounts.counter.roll_window(&clock);
+        if let Some((_, total)) = rolled {
+            entries.push(HistoryEntry::new(
+                HistoryKind::Rollover,
+                total,
+                ctx.accounts.counter.count,
+                authority,
+                clock.slot,
+            ));
+        }
+
+        let counter = &mut ctx.accounts.counter;
+        let previous_value = counter.count;
+        counter.count = new_value;
+        counter.pass_milestones(previous_value);
+        entries.push(HistoryEntry::new(
+            HistoryKind::Set,
+            previous_value,
+            new_value,
+            authority,
+            clock.slot,
+        ));
+        record_history(counter, ctx.remaining_accounts, &entries)?;
+
+        msg!("Counter set from {} to: {}", previous_value, new_value);
+
+        if let Some((window_index, total)) = rolled {
+            emit_event!(
+                ctx,
+                WindowClosed {
+                    counter: ctx.accounts.counter.key(),
+                    window_index,
+                    total,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+        emit_event!(
+            ctx,
+            CounterUpdated {
+                counter: ctx.accounts.counter.key(),
+                previous_value,
+                new_value,
+                delta: i128::from(new_value) - i128::from(previous_value),
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Reset the counter to its minimum (0 unless configured otherwise) at
+    /// once; `schedule_reset` is the time-locked alternative, and the only
+    /// one once `set_require_scheduled_reset` is on
+    pub fn reset<'info>(ctx: Context<'_, '_, 'info, 'info, Update<'info>>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let counter = &mut ctx.accounts.counter;
+        counter.check_instant_reset()?;
+        let previous_value = counter.count;
+        counter.count = counter.min;
+        record_history(
+            counter,
+            ctx.remaining_accounts,
+            &[HistoryEntry::new(
+                HistoryKind::Reset,
+                previous_value,
+                counter.count,
+                authority,
+                Clock::get()?.slot,
+            )],
+        )?;
+
+        msg!("Counter reset to: {}", counter.count);
+
+        emit_event!(
+            ctx,
+            CounterReset {
+                counter: counter.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Apply `ops` in order, all or nothing, after rolling the window over
+    /// if a new one has started.
+    ///
+    /// Every op goes through the same bounds and overflow policy as its
+    /// single-instruction counterpart, so one failing op rejects the whole
+    /// batch. `Reset` and `Set` are rejected while the counter requires
+    /// scheduled resets. Emits one `CounterOpsApplied` summary, preceded by a
+    /// `CounterUpdated` per op when `emit_each` is set.
+    pub fn apply_ops<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Update<'info>>,
+        ops: Vec<CounterOp>,
+        emit_each: bool,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let timestamp = clock.unix_timestamp;
+        let authority = ctx.accounts.authority.key();
+        let mut entries = Vec::with_capacity(ops.len() + 1);
+        let rolled = ctx.accounts.counter.roll_window(&clock);
+        if let Some((_, total)) = rolled {
+            entries.push(HistoryEntry::new(
+                HistoryKind::Rollover,
+                total,
+                ctx.accounts.counter.count,
+                authority,
+                clock.slot,
+            ));
+        }
+        let initial_value = ctx.accounts.counter.count;
+        let mut updates = Vec::new();
+
+        for op in &ops {
+            let previous_value = ctx.accounts.counter.count;
+            ctx.accounts.counter.apply_op(op)?;
+            ctx.accounts.counter.pass_milestones(previous_value);
+            entries.push(HistoryEntry::new(
+                op.history_kind(),
+                previous_value,
+                ctx.accounts.counter.count,
+                authority,
+                clock.slot,
+            ));
+
+            // Emitted once the whole batch has gone through, so a batch
+            // rejected by a later op emits nothing
+            if emit_each {
+                let new_value = ctx.accounts.counter.count;
+                updates.push(CounterUpdated {
+                    counter: ctx.accounts.counter.key(),
+                    previous_value,
+                    new_value,
+                    delta: i128::from(new_value) - i128::from(previous_value),
+                    timestamp,
+                });
+            }
+        }
+
+        ctx.accounts.counter.last_slot = clock.slot;
+        record_history(&ctx.accounts.counter, ctx.remaining_accounts, &entries)?;
+
+        if let Some((window_index, total)) = rolled {
+            emit_event!(
+                ctx,
+                WindowClosed {
+                    counter: ctx.accounts.counter.key(),
+                    window_index,
+                    total,
+                    timestamp,
+                }
+            );
+        }
+        for update in updates {
+            emit_event!(ctx, update);
+        }
+        let counter = &ctx.accounts.counter;
+
+        msg!(
+            "Applied {} ops, counter moved from {} to: {}",
+            ops.len(),
+            initial_value,
+            counter.count
+        );
+
+        emit_event!(
+            ctx,
+            CounterOpsApplied {
+                counter: counter.key(),
+                previous_value: initial_value,
+                new_value: counter.count,
+                ops: ops.len() as u32,
+                timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Schedule a reset that `execute_reset` can carry out once `delay_secs`
+    /// have passed, and until `cancel_reset` withdraws it
+    pub fn schedule_reset(ctx: Context<Update>, delay_secs: u32) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        require!(
+            counter.pending_reset_at.is_none(),
+            CounterError::ResetAlreadyScheduled
+        );
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let unlock_at = timestamp + i64::from(delay_secs);
+        counter.pending_reset_at = Some(unlock_at);
+
+        msg!("Counter reset scheduled for {}", unlock_at);
+
+        emit_event!(
+            ctx,
+            ResetScheduled {
+                counter: counter.key(),
+                unlock_at,
+                timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Carry out a scheduled reset. It unlocks at `pending_reset_at` and
+    /// stays executable for `Counter::RESET_WINDOW_SECS` after that.
+    pub fn execute_reset<'info>(ctx: Context<'_, '_, 'info, 'info, Update<'info>>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let counter = &mut ctx.accounts.counter;
+        let unlock_at = counter
+            .pending_reset_at
+            .ok_or(CounterError::NoPendingReset)?;
+
+        let clock = Clock::get()?;
+        let timestamp = clock.unix_timestamp;
+        if timestamp < unlock_at {
+            msg!("Reset locked until {}", unlock_at);
+            return err!(CounterError::ResetLocked);
+        }
+        require!(
+            timestamp <= unlock_at.saturating_add(Counter::RESET_WINDOW_SECS),
+            CounterError::ResetWindowClosed
+        );
+
+        counter.pending_reset_at = None;
+        let previous_value = counter.count;
+        counter.count = counter.min;
+        record_history(
+            counter,
+            ctx.remaining_accounts,
+            &[HistoryEntry::new(
+                HistoryKind::Reset,
+                previous_value,
+                counter.count,
+                authority,
+                clock.slot,
+            )],
+        )?;
+
+        msg!(
+            "Scheduled reset executed, counter reset to: {}",
+            counter.count
+        );
+
+        emit_event!(
+            ctx,
+            CounterReset {
+                counter: counter.key(),
+                timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Withdraw a scheduled reset. Works while the counter is paused.
+    pub fn cancel_reset(ctx: Context<UpdateAuthority>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        let unlock_at = counter
+            .pending_reset_at
+            .take()
+            .ok_or(CounterError::NoPendingReset)?;
+
+        msg!("Counter reset scheduled for {} cancelled", unlock_at);
+
+        emit_event!(
+            ctx,
+            ResetCancelled {
+                counter: counter.key(),
+                unlock_at,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Require resets to go through `schedule_reset` and `execute_reset`,
+    /// rejecting `reset`, `compare_and_set` and batch `Reset`/`Set` ops, or
+    /// allow them again
+    pub fn set_require_scheduled_reset(
+        ctx: Context<UpdateAuthority>,
+        required: bool,
+    ) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.require_scheduled_reset = required;
+
+        msg!("Counter requires scheduled resets: {}", required);
+
+        emit_event!(
+            ctx,
+            ResetLockConfigured {
+                counter: counter.key(),
+                require_scheduled_reset: required,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Pause the counter, blocking increments, decrements and resets
+    pub fn pause(ctx: Context<UpdateAuthority>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        require!(!counter.paused, CounterError::Paused);
+        counter.paused = true;
+
+        msg!("Counter paused");
+
+        emit_event!(
+            ctx,
+            CounterPaused {
+                counter: counter.key(),
+                authority: counter.authority,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Resume a paused counter
+    pub fn unpause(ctx: Context<UpdateAuthority>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        require!(counter.paused, CounterError::NotPaused);
+        counter.paused = false;
+
+        msg!("Counter unpaused");
+
+        emit_event!(
+            ctx,
+            CounterUnpaused {
+                counter: counter.key(),
+                authority: counter.authority,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Grant an operator the right to increment and decrement the counter
+    pub fn add_operator(ctx: Context<UpdateAuthority>, operator: Pubkey) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        require!(
+            !counter.operators.contains(&operator),
+            CounterError::OperatorAlreadyAdded
+        );
+        require!(
+            counter.operators.len() < Counter::MAX_OPERATORS,
+            CounterError::TooManyOperators
+        );
+        counter.operators.push(operator);
+
+        msg!("Operator added: {}", operator);
+
+        emit_event!(
+            ctx,
+            OperatorAdded {
+                counter: counter.key(),
+                operator,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Revoke a previously granted operator
+    pub fn remove_operator(ctx: Context<UpdateAuthority>, operator: Pubkey) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        let index = counter
+            .operators
+            .iter()
+            .position(|key| *key == operator)
+            .ok_or(CounterError::OperatorNotFound)?;
+        counter.operators.swap_remove(index);
+
+        msg!("Operator removed: {}", operator);
+
+        emit_event!(
+            ctx,
+            OperatorRemoved {
+                counter: counter.key(),
+                operator,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Restrict increments and decrements to holders of at least
+    /// `min_balance` of `gate_mint`, or lift the restriction with `None`.
+    /// Replaces a collection gate.
+    pub fn set_gate(
+        ctx: Context<UpdateAuthority>,
+        gate_mint: Option<Pubkey>,
+        min_balance: u64,
+    ) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.gate_mint = gate_mint;
+        counter.min_balance = if gate_mint.is_some() { min_balance } else { 0 };
+        counter.gate_collection = None;
+
+        match gate_mint {
+            Some(mint) => msg!("Counter gated on {} of mint {}", counter.min_balance, mint),
+            None => msg!("Counter gate removed"),
+        }
+
+        emit_event!(
+            ctx,
+            GateConfigured {
+                counter: counter.key(),
+                gate_mint,
+                min_balance: counter.min_balance,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
 
-        msg!("Counter incremented to: {}", counter.count);
+    /// Restrict increments and decrements to holders of an NFT whose
+    /// Metaplex metadata places it in the verified `collection`, or lift the
+    /// restriction with `None`. Replaces a mint gate.
+    pub fn set_collection_gate(
+        ctx: Context<UpdateAuthority>,
+        collection: Option<Pubkey>,
+    ) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.gate_collection = collection;
+        counter.gate_mint = None;
+        counter.min_balance = 0;
+
+        match collection {
+            Some(collection) => msg!("Counter gated on collection {}", collection),
+            None => msg!("Counter gate removed"),
+        }
+
+        emit_event!(
+            ctx,
+            CollectionGateConfigured {
+                counter: counter.key(),
+                collection,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Require `cooldown_slots` slots between any two increments or
+    /// decrements of the counter, and `caller_cooldown_slots` between two by
+    /// the same signer. 0 disables either limit.
+    pub fn set_cooldown(
+        ctx: Context<UpdateAuthority>,
+        cooldown_slots: u64,
+        caller_cooldown_slots: u64,
+    ) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.cooldown_slots = cooldown_slots;
+        counter.caller_cooldown_slots = caller_cooldown_slots;
+
+        msg!(
+            "Counter cooldown set to {} slots, {} per caller",
+            cooldown_slots,
+            caller_cooldown_slots
+        );
 
-        emit!(CounterUpdated {
-            counter: ctx.accounts.counter.key(),
-            new_value: counter.count,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+        emit_event!(
+            ctx,
+            CooldownConfigured {
+                counter: counter.key(),
+                cooldown_slots,
+                caller_cooldown_slots,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
 
         Ok(())
     }
 
-    /// Decrement the counter by 1
-    pub fn decrement(ctx: Context<Update>) -> Result<()> {
+    /// Make the count roll back to its minimum whenever a new `window`
+    /// starts, or keep it running with `None`.
+    ///
+    /// Windows are aligned to the Unix epoch (`Seconds`) or to epoch 0
+    /// (`Epochs`), so `Seconds(86_400)` gives UTC days. The roll happens on
+    /// the first increment or decrement of a new window, which archives the
+    /// count in `previous_window_total` and emits `WindowClosed`.
+    pub fn set_window(ctx: Context<UpdateAuthority>, window: Option<RollingWindow>) -> Result<()> {
+        if let Some(window) = window {
+            require!(window.length() > 0, CounterError::InvalidWindow);
+        }
+
+        let clock = Clock::get()?;
         let counter = &mut ctx.accounts.counter;
+        counter.window = window;
+        counter.window_index = window.map_or(0, |window| window.index(&clock));
+
+        match window {
+            Some(window) => msg!("Counter window set to {:?}", window),
+            None => msg!("Counter window removed"),
+        }
+
+        emit_event!(
+            ctx,
+            WindowConfigured {
+                counter: counter.key(),
+                window,
+                window_index: counter.window_index,
+                timestamp: clock.unix_timestamp,
+            }
+        );
 
+        Ok(())
+    }
+
+    /// Reward whoever's increment first takes the count to each milestone's
+    /// threshold with the milestone's amount of `reward_mint`, or stop
+    /// rewarding with an empty list.
+    ///
+    /// The counter must be the mint authority of `reward_mint`. Every
+    /// milestone pays out once; one already reached stays reached when it is
+    /// registered again with the same threshold.
+    pub fn set_milestones(
+        ctx: Context<SetMilestones>,
+        milestones: Vec<MilestoneConfig>,
+    ) -> Result<()> {
+        require!(
+            milestones.len() <= Counter::MAX_MILESTONES,
+            CounterError::TooManyMilestones
+        );
         require!(
-            counter.count > 0,
-            CounterError::Underflow
+            milestones
+                .windows(2)
+                .all(|pair| pair[0].threshold < pair[1].threshold),
+            CounterError::InvalidMilestones
+        );
+
+        let reward_mint = if milestones.is_empty() {
+            None
+        } else {
+            let mint = ctx
+                .accounts
+                .reward_mint
+                .as_ref()
+                .ok_or(CounterError::InvalidRewardMint)?;
+            require!(
+                mint.mint_authority == COption::Some(ctx.accounts.counter.key()),
+                CounterError::InvalidRewardMint
+            );
+            Some(mint.key())
+        };
+
+        let counter = &mut ctx.accounts.counter;
+        let previous = std::mem::take(&mut counter.milestones);
+        counter.milestones = milestones
+            .iter()
+            .map(|config| Milestone {
+                threshold: config.threshold,
+                reward: config.reward,
+                reached: previous
+                    .iter()
+                    .any(|milestone| milestone.threshold == config.threshold && milestone.reached),
+            })
+            .collect();
+        counter.reward_mint = reward_mint;
+
+        msg!("Counter milestones set: {}", counter.milestones.len());
+
+        emit_event!(
+            ctx,
+            MilestonesConfigured {
+                counter: counter.key(),
+                reward_mint,
+                milestones: counter.milestones.clone(),
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Create the counter's history account at `[b"history", counter]`.
+    ///
+    /// From then on every instruction that changes the count must pass the
+    /// history account as its first remaining account, and appends what it
+    /// did to the account's ring buffer of the last `HISTORY_CAPACITY`
+    /// operations.
+    pub fn init_history(ctx: Context<InitHistory>) -> Result<()> {
+        ctx.accounts.history.load_init()?.counter = ctx.accounts.counter.key();
+
+        let counter = &mut ctx.accounts.counter;
+        counter.has_history = true;
+
+        msg!("Counter history initialized");
+
+        emit_event!(
+            ctx,
+            HistoryInitialized {
+                counter: counter.key(),
+                history: ctx.accounts.history.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Close the counter's history account and return its rent to the
+    /// authority. Updates stop taking the account, and a new one starts
+    /// empty.
+    pub fn close_history(ctx: Context<CloseHistory>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.has_history = false;
+
+        msg!("Counter history closed");
+
+        emit_event!(
+            ctx,
+            HistoryClosed {
+                counter: counter.key(),
+                history: ctx.accounts.history.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Change the bounds and overflow policy of the counter
+    pub fn configure(ctx: Context<UpdateAuthority>, config: CounterConfig) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.apply_config(&config)?;
+
+        msg!(
+            "Counter configured: min {}, max {}, policy {:?}",
+            config.min,
+            config.max,
+            config.overflow_policy
+        );
+
+        emit_event!(
+            ctx,
+            CounterConfigured {
+                counter: counter.key(),
+                min: config.min,
+                max: config.max,
+                overflow_policy: config.overflow_policy,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Propose a new authority; control moves only once it accepts
+    pub fn propose_authority(ctx: Context<UpdateAuthority>, new_authority: Pubkey) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.pending_authority = Some(new_authority);
+
+        msg!("Authority transfer proposed to {}", new_authority);
+
+        emit_event!(
+            ctx,
+            AuthorityProposed {
+                counter: counter.key(),
+                authority: counter.authority,
+                pending_authority: new_authority,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Accept a proposed authority transfer, signed by the pending authority
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        let pending_authority = counter
+            .pending_authority
+            .ok_or(CounterError::NoPendingAuthority)?;
+
+        require_keys_eq!(
+            pending_authority,
+            ctx.accounts.pending_authority.key(),
+            CounterError::NotPendingAuthority
         );
 
-        counter.count = counter.count.checked_sub(1)
-            .ok_or(CounterError::Underflow)?;
+        let old_authority = counter.authority;
+        counter.authority = pending_authority;
+        counter.pending_authority = None;
 
-        msg!("Counter decremented to: {}", counter.count);
+        msg!(
+            "Authority transferred from {} to {}",
+            old_authority,
+            pending_authority
+        );
 
-        emit!(CounterUpdated {
-            counter: ctx.accounts.counter.key(),
-            new_value: counter.count,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+        emit_event!(
+            ctx,
+            AuthorityAccepted {
+                counter: counter.key(),
+                old_authority,
+                new_authority: pending_authority,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
 
         Ok(())
     }
 
-    /// Reset the counter to 0
-    pub fn reset(ctx: Context<Update>) -> Result<()> {
+    /// Cancel a proposed authority transfer
+    pub fn cancel_authority_transfer(ctx: Context<UpdateAuthority>) -> Result<()> {
         let counter = &mut ctx.accounts.counter;
-        counter.count = 0;
+        let pending_authority = counter
+            .pending_authority
+            .take()
+            .ok_or(CounterError::NoPendingAuthority)?;
+
+        msg!("Authority transfer to {} cancelled", pending_authority);
+
+        emit_event!(
+            ctx,
+            AuthorityTransferCancelled {
+                counter: counter.key(),
+                authority: counter.authority,
+                pending_authority,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Transfer `amount` of the collected fees from the vault to `destination`
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let counter = &ctx.accounts.counter;
+        require!(
+            ctx.accounts.fee_vault.amount >= amount,
+            CounterError::InsufficientBalance
+        );
+
+        let counter_id = counter.counter_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"counter",
+            counter.creator.as_ref(),
+            &counter_id,
+            &[counter.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.fee_vault.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: counter.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        msg!(
+            "Withdrew {} in fees to {}",
+            amount,
+            ctx.accounts.destination.key()
+        );
+
+        emit_event!(
+            ctx,
+            FeesWithdrawn {
+                counter: counter.key(),
+                destination: ctx.accounts.destination.key(),
+                amount,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Close the counter and return its rent to the authority.
+    ///
+    /// The PDA is freed, so the creator can `initialize` it again afterwards.
+    /// A counter that charges fees also closes its vault, which must be
+    /// empty, and a counter with a history account must close it first.
+    pub fn close(ctx: Context<CloseCounter>) -> Result<()> {
+        require!(!ctx.accounts.counter.has_history, CounterError::HistoryOpen);
+        ctx.accounts.close_fee_vault()?;
+        let counter = &ctx.accounts.counter;
 
-        msg!("Counter reset to: 0");
+        msg!("Counter closed at: {}", counter.count);
 
-        emit!(CounterReset {
-            counter: ctx.accounts.counter.key(),
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+        emit_event!(
+            ctx,
+            CounterClosed {
+                counter: counter.key(),
+                authority: counter.authority,
+                final_value: counter.count,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
 
         Ok(())
     }
 
-    /// Update the authority of the counter
-    pub fn update_authority(ctx: Context<UpdateAuthority>, new_authority: Pubkey) -> Result<()> {
-        let counter = &mut ctx.accounts.counter;
-        let old_authority = counter.authority;
+    /// Close the caller's contribution record and return its rent to them.
+    ///
+    /// The record holds the caller's last slot, so it cannot be closed
+    /// while they are within the counter's per-caller cooldown; a fresh
+    /// record would let them skip it.
+    pub fn close_contribution(ctx: Context<CloseContribution>) -> Result<()> {
+        let contribution = &ctx.accounts.contribution;
+        let info = ctx.accounts.counter.to_account_info();
+        if *info.owner == crate::ID {
+            let data = info.try_borrow_data()?;
+            let counter = if data.len() == Counter::LEN {
+                Some(Counter::try_deserialize(&mut &data[..])?)
+            } else {
+                // Not migrated yet; read it as its layout so migrating in
+                // the same transaction cannot skip the check
+                CounterLayout::find(Counter::LAYOUTS, data.len())
+                    .filter(|_| data[..8] == Counter::DISCRIMINATOR)
+                    .map(|layout| Counter::read_layout(&data[8..], layout))
+                    .transpose()?
+            };
+            if let Some(counter) = counter {
+                counter.check_caller_cooldown(contribution.last_slot)?;
+            }
+        }
+
+        msg!(
+            "Contribution to {} closed after {} increments and {} decrements",
+            contribution.counter,
+            contribution.increments,
+            contribution.decrements
+        );
+
+        Ok(())
+    }
+
+    /// Upgrade a counter stored in an older layout to the current one, in
+    /// place.
+    ///
+    /// The account is reallocated to `Counter::LEN` and new fields take their
+    /// defaults. Anyone may migrate a counter; the payer tops up the rent the
+    /// larger account needs.
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        let info = ctx.accounts.counter.to_account_info();
+
+        require_keys_eq!(*info.owner, crate::ID, CounterError::InvalidLegacyAccount);
+
+        let (upgraded, from_version) = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == Counter::DISCRIMINATOR,
+                CounterError::InvalidLegacyAccount
+            );
+            let layout = CounterLayout::find(Counter::LAYOUTS, data.len())
+                .ok_or(CounterError::InvalidLegacyAccount)?;
+            (Counter::read_layout(&data[8..], layout)?, layout.version)
+        };
+
+        let address = Pubkey::create_program_address(
+            &[
+                b"counter",
+                upgraded.creator.as_ref(),
+                &upgraded.counter_id.to_le_bytes(),
+                &[upgraded.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| error!(CounterError::InvalidLegacyAccount))?;
+        require_keys_eq!(address, info.key(), CounterError::InvalidLegacyAccount);
+
+        let top_up = Rent::get()?
+            .minimum_balance(Counter::LEN)
+            .saturating_sub(info.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        info.realloc(Counter::LEN, true)?;
+        upgraded.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!(
+            "Counter migrated in place from layout version {} to {}",
+            from_version,
+            Counter::VERSION
+        );
+
+        emit_event!(
+            ctx,
+            CounterUpgraded {
+                counter: info.key(),
+                from_version,
+                to_version: Counter::VERSION,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Move a counter created before counters were addressed by id.
+    ///
+    /// Those counters live at `[b"counter", creator]` and may use an older
+    /// account layout. The current authority signs, supplies the creator
+    /// (verified against the old address) and picks the `counter_id` the
+    /// counter moves to. The authority pays for the new account and receives
+    /// the old account's rent once it is closed.
+    pub fn migrate_counter(
+        ctx: Context<MigrateCounter>,
+        creator: Pubkey,
+        counter_id: u64,
+    ) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_counter.to_account_info();
+
+        require_keys_eq!(
+            *legacy_info.owner,
+            crate::ID,
+            CounterError::InvalidLegacyAccount
+        );
+
+        let migrated = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == Counter::DISCRIMINATOR,
+                CounterError::InvalidLegacyAccount
+            );
+            let layout = CounterLayout::find(Counter::LEGACY_LAYOUTS, data.len())
+                .ok_or(CounterError::InvalidLegacyAccount)?;
+            Counter {
+                bump: ctx.bumps.counter,
+                creator,
+                counter_id,
+                ..Counter::read_layout(&data[8..], layout)?
+            }
+        };
+
+        require_keys_eq!(
+            migrated.authority,
+            ctx.accounts.authority.key(),
+            CounterError::Unauthorized
+        );
+
+        ctx.accounts.counter.set_inner(migrated);
+        close_account(&legacy_info, &ctx.accounts.authority.to_account_info())?;
+
+        msg!(
+            "Counter migrated for creator {} to id {}",
+            creator,
+            counter_id
+        );
+
+        emit_event!(
+            ctx,
+            CounterMigrated {
+                legacy_counter: legacy_info.key(),
+                counter: ctx.accounts.counter.key(),
+                creator,
+                counter_id,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+}
+
+/// Account structure for the counter
+#[account]
+pub struct Counter {
+    /// Current count value
+    pub count: u64,
+    /// Authority that can modify the counter
+    pub authority: Pubkey,
+    /// Bump seed for PDA
+    pub bump: u8,
+    /// Wallet that created the counter; seeds the PDA so it survives authority changes
+    pub creator: Pubkey,
+    /// Authority proposed by `propose_authority`, awaiting acceptance
+    pub pending_authority: Option<Pubkey>,
+    /// Lowest value the count may take
+    pub min: u64,
+    /// Highest value the count may take
+    pub max: u64,
+    /// What happens when an update would leave `min..=max`
+    pub overflow_policy: OverflowPolicy,
+    /// Identifier chosen by the creator; seeds the PDA next to `creator`
+    pub counter_id: u64,
+    /// Emergency stop set by `pause`; blocks increments, decrements and resets
+    pub paused: bool,
+    /// Keys allowed to increment and decrement alongside the authority
+    pub operators: Vec<Pubkey>,
+    /// Token fee charged per increment or decrement, if any
+    pub fee: Option<Fee>,
+    /// Mint operators must hold to increment or decrement, if gated
+    pub gate_mint: Option<Pubkey>,
+    /// Balance of `gate_mint` operators must hold
+    pub min_balance: u64,
+    /// Slots required between any two increments or decrements, 0 for none
+    pub cooldown_slots: u64,
+    /// Slots required between two increments or decrements by the same
+    /// signer, 0 for none
+    pub caller_cooldown_slots: u64,
+    /// Slot of the latest increment or decrement, 0 if there was none
+    pub last_slot: u64,
+    /// Layout version, `Counter::VERSION` once initialized or migrated
+    pub version: u8,
+    /// Nonce the next `increment_with_permit` must carry
+    pub permit_nonce: u64,
+    /// Unix timestamp from which a reset scheduled by `schedule_reset` can
+    /// be executed
+    pub pending_reset_at: Option<i64>,
+    /// Length of the window after which the count rolls back to `min`, if any
+    pub window: Option<RollingWindow>,
+    /// Index of the window the count belongs to
+    pub window_index: u64,
+    /// Count at the close of the previous window
+    pub previous_window_total: u64,
+    /// Mint milestone rewards are paid in, if any
+    pub reward_mint: Option<Pubkey>,
+    /// Thresholds whose first crossing by an increment pays a reward
+    pub milestones: Vec<Milestone>,
+    /// Whether `init_history` created a history account that updates must
+    /// write to
+    pub has_history: bool,
+    /// Whether resets must be scheduled, rejecting the instant paths
+    pub require_scheduled_reset: bool,
+    /// Verified Metaplex collection operators must hold an NFT of to
+    /// increment or decrement, if gated on a collection
+    pub gate_collection: Option<Pubkey>,
+}
+
+impl Counter {
+    /// Maximum number of operators a counter can hold
+    pub const MAX_OPERATORS: usize = 5;
+
+    /// Maximum number of milestones a counter can hold
+    pub const MAX_MILESTONES: usize = 5;
+
+    /// Current layout version. Bump it, and add the previous layout to
+    /// `LAYOUTS`, whenever fields are added.
+    pub const VERSION: u8 = 9;
+
+    /// Layouts of counters at their id-based address that `migrate`
+    /// upgrades, oldest first
+    pub const LAYOUTS: &'static [CounterLayout] = &[
+        // Counter ids, before the version field
+        CounterLayout::new(1, 9),
+        // Pausing
+        CounterLayout::new(1, 10),
+        // Operators
+        CounterLayout::new(1, 11),
+        // Fees
+        CounterLayout::new(1, 12),
+        // Token gating
+        CounterLayout::new(1, 14),
+        // Cooldowns
+        CounterLayout::new(1, 17),
+        // Layout version
+        CounterLayout::new(2, 18),
+        // Permit nonce
+        CounterLayout::new(3, 19),
+        // Scheduled resets
+        CounterLayout::new(4, 20),
+        // Rolling windows
+        CounterLayout::new(5, 23),
+        // Milestone rewards
+        CounterLayout::new(6, 25),
+        // History accounts
+        CounterLayout::new(7, 26),
+        // Required scheduled resets
+        CounterLayout::new(8, 27),
+    ];
+
+    /// Layouts of counters at the `[b"counter", creator]` address that
+    /// `migrate_counter` moves, oldest first
+    pub const LEGACY_LAYOUTS: &'static [CounterLayout] = &[
+        // Count, authority and bump
+        CounterLayout::new(1, 3),
+        // Creator
+        CounterLayout::new(1, 4),
+        // Pending authority
+        CounterLayout::new(1, 5),
+        // Bounds and overflow policy
+        CounterLayout::new(1, 8),
+    ];
+
+    /// Space each field takes, in declaration order
+    const FIELD_SPACE: [usize; 28] = [
+        8,                                            // count
+        32,                                           // authority
+        1,                                            // bump
+        32,                                           // creator
+        33,                                           // pending_authority
+        8,                                            // min
+        8,                                            // max
+        1,                                            // overflow_policy
+        8,                                            // counter_id
+        1,                                            // paused
+        4 + 32 * Counter::MAX_OPERATORS,              // operators
+        1 + Fee::LEN,                                 // fee
+        33,                                           // gate_mint
+        8,                                            // min_balance
+        8,                                            // cooldown_slots
+        8,                                            // caller_cooldown_slots
+        8,                                            // last_slot
+        1,                                            // version
+        8,                                            // permit_nonce
+        9,                                            // pending_reset_at
+        1 + RollingWindow::LEN,                       // window
+        8,                                            // window_index
+        8,                                            // previous_window_total
+        33,                                           // reward_mint
+        4 + Milestone::LEN * Counter::MAX_MILESTONES, // milestones
+        1,                                            // has_history
+        1,                                            // require_scheduled_reset
+        33,                                           // gate_collection
+    ];
+
+    /// Seconds a scheduled reset stays executable once it unlocks
+    pub const RESET_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+    /// Space required for the Counter account
+    pub const LEN: usize = Counter::space(Counter::FIELD_SPACE.len());
+
+    /// Space taken by 8 (discriminator) and the first `fields` fields
+    pub const fn space(fields: usize) -> usize {
+        let mut space = 8;
+        let mut field = 0;
+        while field < fields {
+            space += Counter::FIELD_SPACE[field];
+            field += 1;
+        }
+        space
+    }
+
+    /// Decode `data`, the fields after the discriminator of an account in
+    /// `layout`, giving the fields it predates their defaults. The version
+    /// is set to `Counter::VERSION`.
+    pub fn read_layout(data: &[u8], layout: CounterLayout) -> Result<Counter> {
+        let config = CounterConfig::default();
+        let mut reader = LayoutReader {
+            data,
+            fields: layout.fields,
+        };
+        let mut counter = Counter {
+            count: reader.read()?,
+            authority: reader.read()?,
+            bump: reader.read()?,
+            creator: reader.read()?,
+            pending_authority: reader.read()?,
+            min: reader.read_or(config.min)?,
+            max: reader.read_or(config.max)?,
+            overflow_policy: reader.read_or(config.overflow_policy)?,
+            counter_id: reader.read()?,
+            paused: reader.read()?,
+            operators: reader.read()?,
+            fee: reader.read()?,
+            gate_mint: reader.read()?,
+            min_balance: reader.read()?,
+            cooldown_slots: reader.read()?,
+            caller_cooldown_slots: reader.read()?,
+            last_slot: reader.read()?,
+            version: reader.read()?,
+            permit_nonce: reader.read()?,
+            pending_reset_at: reader.read()?,
+            window: reader.read()?,
+            window_index: reader.read()?,
+            previous_window_total: reader.read()?,
+            reward_mint: reader.read()?,
+            milestones: reader.read()?,
+            has_history: reader.read()?,
+            require_scheduled_reset: reader.read()?,
+            gate_collection: reader.read()?,
+        };
+        counter.version = Counter::VERSION;
+        Ok(counter)
+    }
+
+    /// Whether `key` may increment or decrement: the authority or an operator
+    pub fn is_operator(&self, key: &Pubkey) -> bool {
+        *key == self.authority || self.operators.contains(key)
+    }
+
+    /// Apply a signed change to the count according to the overflow policy
+    pub fn apply_delta(&mut self, delta: i128) -> Result<()> {
+        let min = i128::from(self.min);
+        let max = i128::from(self.max);
+        let target = i128::from(self.count) + delta;
+
+        let value = match self.overflow_policy {
+            OverflowPolicy::Error => {
+                require!(target <= max, CounterError::Overflow);
+                require!(target >= min, CounterError::Underflow);
+                target
+            }
+            OverflowPolicy::Saturate => target.clamp(min, max),
+            OverflowPolicy::Wrap => min + (target - min).rem_euclid(max - min + 1),
+        };
+
+        // `value` always lies within `min..=max`, so it fits in a u64
+        self.count = value as u64;
+        Ok(())
+    }
+
+    /// Apply one op of an `apply_ops` batch
+    pub fn apply_op(&mut self, op: &CounterOp) -> Result<()> {
+        match *op {
+            CounterOp::Increment => self.apply_delta(1),
+            CounterOp::Decrement => self.apply_delta(-1),
+            CounterOp::IncrementBy(amount) => self.apply_delta(i128::from(amount)),
+            CounterOp::DecrementBy(amount) => self.apply_delta(-i128::from(amount)),
+            CounterOp::Reset => {
+                self.check_instant_reset()?;
+                self.count = self.min;
+                Ok(())
+            }
+            CounterOp::Set(value) => {
+                self.check_instant_reset()?;
+                require!(
+                    (self.min..=self.max).contains(&value),
+                    CounterError::OutOfBounds
+                );
+                self.count = value;
+                Ok(())
+            }
+        }
+    }
+
+    /// Fail with `ScheduledResetRequired` if resets must go through
+    /// `schedule_reset`
+    pub fn check_instant_reset(&self) -> Result<()> {
+        require!(
+            !self.require_scheduled_reset,
+            CounterError::ScheduledResetRequired
+        );
+        Ok(())
+    }
+
+    /// Fail with `RateLimited` if the counter was updated within its
+    /// cooldown, or the caller, last active at `caller_last_slot`, within
+    /// theirs. A last slot of 0 means no earlier update.
+    pub fn check_cooldown(&self, caller_last_slot: u64) -> Result<()> {
+        check_cooldowns(&[
+            (self.last_slot, self.cooldown_slots),
+            (caller_last_slot, self.caller_cooldown_slots),
+        ])
+    }
+
+    /// Fail with `RateLimited` if the caller, last active at
+    /// `caller_last_slot`, is still within their cooldown
+    pub fn check_caller_cooldown(&self, caller_last_slot: u64) -> Result<()> {
+        check_cooldowns(&[(caller_last_slot, self.caller_cooldown_slots)])
+    }
+
+    /// Start a new window if `clock` has moved past the current one: archive
+    /// the count in `previous_window_total` and return it to the minimum.
+    /// Returns the index and total of the window that closed.
+    pub fn roll_window(&mut self, clock: &Clock) -> Option<(u64, u64)> {
+        let index = self.next_window(clock)?;
+        let closed = (self.window_index, self.count);
+        self.window_index = index;
+        self.previous_window_total = self.count;
+        self.count = self.min;
+        Some(closed)
+    }
+
+    /// Index of the window `clock` falls in, if it has moved past the
+    /// current one
+    pub fn next_window(&self, clock: &Clock) -> Option<u64> {
+        let index = self.window?.index(clock);
+        (index > self.window_index).then_some(index)
+    }
+
+    /// Mark the milestones the count reached on its way up from
+    /// `previous_value` and return them. A milestone is only returned the
+    /// first time, so re-crossing its threshold pays nothing.
+    pub fn reach_milestones(&mut self, previous_value: u64) -> Vec<Milestone> {
+        let count = self.count;
+        self.milestones
+            .iter_mut()
+            .filter(|milestone| {
+                !milestone.reached
+                    && previous_value < milestone.threshold
+                    && milestone.threshold <= count
+            })
+            .map(|milestone| {
+                milestone.reached = true;
+                *milestone
+            })
+            .collect()
+    }
+
+    /// Mark the milestones an update that pays no rewards took the count
+    /// past, so they cannot be collected by a later increment
+    pub fn pass_milestones(&mut self, previous_value: u64) {
+        for milestone in self.reach_milestones(previous_value) {
+            msg!("Milestone {} passed without a reward", milestone.threshold);
+        }
+    }
+
+    /// Fail with `StaleValue` if `expected` is set and differs from the count
+    /// an update at `clock` starts from: the minimum if the window rolls
+    /// over first
+    pub fn check_expected(&self, expected: Option<u64>, clock: &Clock) -> Result<()> {
+        let count = if self.next_window(clock).is_some() {
+            self.min
+        } else {
+            self.count
+        };
+        if let Some(expected) = expected {
+            if count != expected {
+                msg!("Stale value: expected {}, observed {}", expected, count);
+                return err!(CounterError::StaleValue);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the bounds and overflow policy, keeping the current count
+    pub fn apply_config(&mut self, config: &CounterConfig) -> Result<()> {
+        require!(config.min <= config.max, CounterError::InvalidBounds);
+        require!(
+            (config.min..=config.max).contains(&self.count),
+            CounterError::OutOfBounds
+        );
+
+        self.min = config.min;
+        self.max = config.max;
+        self.overflow_policy = config.overflow_policy;
+        Ok(())
+    }
+}
+
+/// Behaviour when an update would move the count outside its bounds
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail with `Overflow` / `Underflow`
+    #[default]
+    Error,
+    /// Clamp to the nearest bound
+    Saturate,
+    /// Wrap around to the opposite bound
+    Wrap,
+}
+
+/// One step of an `apply_ops` batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterOp {
+    /// Add 1, like `increment`
+    Increment,
+    /// Subtract 1, like `decrement`
+    Decrement,
+    /// Add an amount, like `increment_by`
+    IncrementBy(u64),
+    /// Subtract an amount, like `decrement_by`
+    DecrementBy(u64),
+    /// Return to the minimum, like `reset`
+    Reset,
+    /// Set the count to a value within the bounds
+    Set(u64),
+}
+
+impl CounterOp {
+    /// How the operation is recorded in the counter's history
+    pub fn history_kind(&self) -> HistoryKind {
+        match self {
+            CounterOp::Increment | CounterOp::IncrementBy(_) => HistoryKind::Increment,
+            CounterOp::Decrement | CounterOp::DecrementBy(_) => HistoryKind::Decrement,
+            CounterOp::Reset => HistoryKind::Reset,
+            CounterOp::Set(_) => HistoryKind::Set,
+        }
+    }
+}
+
+/// Length of the windows a counter's count rolls over in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RollingWindow {
+    /// Windows of this many seconds of cluster time
+    Seconds(u64),
+    /// Windows of this many Solana epochs
+    Epochs(u64),
+}
+
+impl RollingWindow {
+    /// 1 (variant) + 8 (length)
+    pub const LEN: usize = 1 + 8;
+
+    /// Window length in its own unit
+    pub fn length(&self) -> u64 {
+        match *self {
+            RollingWindow::Seconds(length) | RollingWindow::Epochs(length) => length,
+        }
+    }
+
+    /// Index of the window `clock` falls in. The length must not be 0.
+    pub fn index(&self, clock: &Clock) -> u64 {
+        match *self {
+            RollingWindow::Seconds(length) => clock.unix_timestamp.max(0) as u64 / length,
+            RollingWindow::Epochs(length) => clock.epoch / length,
+        }
+    }
+}
+
+/// Threshold and reward supplied to `set_milestones`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MilestoneConfig {
+    pub threshold: u64,
+    pub reward: u64,
+}
+
+/// Reward for the first increment that takes the count to `threshold`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Milestone {
+    /// Count at which the milestone is reached
+    pub threshold: u64,
+    /// Base units of the reward mint minted to the caller
+    pub reward: u64,
+    /// Whether the reward has been paid
+    pub reached: bool,
+}
+
+impl Milestone {
+    /// 8 (threshold) + 8 (reward) + 1 (reached)
+    pub const LEN: usize = 8 + 8 + 1;
+}
+
+/// Bounds and overflow policy supplied to `initialize` and `configure`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct CounterConfig {
+    pub min: u64,
+    pub max: u64,
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for CounterConfig {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: u64::MAX,
+            overflow_policy: OverflowPolicy::Error,
+        }
+    }
+}
+
+/// One user's increments and decrements on one counter, created on their
+/// first operation at `[b"contribution", counter, user]`.
+///
+/// The layout is fixed-size, so every field sits at a constant offset and
+/// `counter` (offset 8) and `user` (offset 40) can be filtered on with
+/// `getProgramAccounts`.
+#[account]
+pub struct Contribution {
+    /// Counter the record belongs to
+    pub counter: Pubkey,
+    /// Wallet that made the operations
+    pub user: Pubkey,
+    /// Number of increments, including `increment_by`
+    pub increments: u64,
+    /// Number of decrements, including `decrement_by`
+    pub decrements: u64,
+    /// Slot of the latest operation, 0 for a new record
+    pub last_slot: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl Contribution {
+    /// 8 (discriminator) + 32 (counter) + 32 (user) + 8 (increments)
+    /// + 8 (decrements) + 8 (last_slot) + 1 (bump)
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+/// Number of operations a history account keeps
+#[constant]
+pub const HISTORY_CAPACITY: usize = 32;
+
+/// The last `HISTORY_CAPACITY` operations on one counter, created by
+/// `init_history` at `[b"history", counter]`.
+///
+/// The account is zero-copy and fixed-size, so the whole buffer is read with
+/// one `getAccountInfo` and updates never deserialize it. `entries` is a ring
+/// buffer: the newest entry sits just before `head`, and once `len` reaches
+/// the capacity each update overwrites the oldest.
+#[account(zero_copy)]
+pub struct History {
+    /// Counter the operations were made on
+    pub counter: Pubkey,
+    /// Index the next entry is written at
+    pub head: u32,
+    /// Number of entries written, up to `HISTORY_CAPACITY`
+    pub len: u32,
+    pub entries: [HistoryEntry; HISTORY_CAPACITY],
+}
+
+impl History {
+    /// 8 (discriminator) + 32 (counter) + 4 (head) + 4 (len)
+    /// + HistoryEntry::LEN * HISTORY_CAPACITY (entries)
+    pub const LEN: usize = 8 + 32 + 4 + 4 + HistoryEntry::LEN * HISTORY_CAPACITY;
+
+    /// Append `entry`, overwriting the oldest entry once the buffer is full
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries[self.head as usize] = entry;
+        self.head = (self.head + 1) % HISTORY_CAPACITY as u32;
+        self.len = (self.len + 1).min(HISTORY_CAPACITY as u32);
+    }
+
+    /// Entries from oldest to newest
+    pub fn recent(&self) -> impl Iterator<Item = &HistoryEntry> {
+        let start = self.head as usize + HISTORY_CAPACITY - self.len as usize;
+        (0..self.len as usize).map(move |i| &self.entries[(start + i) % HISTORY_CAPACITY])
+    }
+}
+
+/// One operation in a counter's history
+#[zero_copy]
+#[derive(Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Slot the operation landed in
+    pub slot: u64,
+    /// Count after the operation
+    pub value: u64,
+    /// Change to the count, saturated to the range of an i64
+    pub delta: i64,
+    /// Signer of the operation; the authority for permits
+    pub actor: Pubkey,
+    /// `HistoryKind` of the operation, as its variant index
+    pub kind: u8,
+    pub _padding: [u8; 7],
+}
+
+impl HistoryEntry {
+    /// 8 (slot) + 8 (value) + 8 (delta) + 32 (actor) + 1 (kind) + 7 (padding)
+    pub const LEN: usize = 8 + 8 + 8 + 32 + 1 + 7;
+
+    /// Record an operation that took the count from `previous_value` to `value`
+    pub fn new(
+        kind: HistoryKind,
+        previous_value: u64,
+        value: u64,
+        actor: Pubkey,
+        slot: u64,
+    ) -> Self {
+        let delta = i128::from(value) - i128::from(previous_value);
+        Self {
+            slot,
+            value,
+            delta: delta.clamp(i64::MIN.into(), i64::MAX.into()) as i64,
+            actor,
+            kind: kind as u8,
+            _padding: [0; 7],
+        }
+    }
+
+    /// The kind of operation, or `None` if `kind` holds no known kind
+    pub fn history_kind(&self) -> Option<HistoryKind> {
+        match self.kind {
+            0 => Some(HistoryKind::Increment),
+            1 => Some(HistoryKind::Decrement),
+            2 => Some(HistoryKind::Set),
+            3 => Some(HistoryKind::Reset),
+            4 => Some(HistoryKind::Rollover),
+            _ => None,
+        }
+    }
+}
+
+/// What a history entry records
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// `increment`, `increment_by`, a permit or an increment in a batch
+    Increment,
+    /// `decrement`, `decrement_by` or a decrement in a batch
+    Decrement,
+    /// `compare_and_set` or a set in a batch
+    Set,
+    /// `reset`, `execute_reset` or a reset in a batch
+    Reset,
+    /// The count rolling back to its minimum as a new window started
+    Rollover,
+}
+
+/// Increment authorised off-chain by a counter's authority for
+/// `increment_with_permit`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permit {
+    /// Counter the permit applies to
+    pub counter: Pubkey,
+    /// Must equal the counter's `permit_nonce` when submitted
+    pub nonce: u64,
+    /// Amount to increment by
+    pub delta: u64,
+    /// Unix timestamp after which the permit is rejected
+    pub expiry: i64,
+}
+
+impl Permit {
+    /// Prefix that keeps permit signatures from doubling as other messages
+    pub const DOMAIN: &'static [u8] = b"counter_program:permit";
+
+    /// Bytes the authority signs: `DOMAIN` followed by the counter and the
+    /// little-endian nonce, delta and expiry
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(self.counter.as_ref());
+        message.extend_from_slice(&self.nonce.to_le_bytes());
+        message.extend_from_slice(&self.delta.to_le_bytes());
+        message.extend_from_slice(&self.expiry.to_le_bytes());
+        message
+    }
+}
+
+/// Per-operation token fee set up by `initialize`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fee {
+    /// Mint the fee is paid in
+    pub mint: Pubkey,
+    /// Token account owned by the counter that collects the fees
+    pub vault: Pubkey,
+    /// Base units of `mint` charged per increment or decrement
+    pub amount: u64,
+}
+
+impl Fee {
+    /// 32 (mint) + 32 (vault) + 8 (amount)
+    pub const LEN: usize = 32 + 32 + 8;
+}
+
+/// A layout `Counter` accounts were stored in before the current one.
+///
+/// Fields are only ever appended to `Counter`, so every layout holds the
+/// first `fields` fields of the current struct in accounts of
+/// `Counter::space(fields)` bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterLayout {
+    /// Layout version; layouts from before the `version` field count as 1
+    pub version: u8,
+    /// Number of leading `Counter` fields the layout stores
+    pub fields: usize,
+}
+
+impl CounterLayout {
+    /// Layout `version`, holding the first `fields` fields
+    pub const fn new(version: u8, fields: usize) -> Self {
+        Self { version, fields }
+    }
+
+    /// Size of accounts in this layout
+    pub const fn space(&self) -> usize {
+        Counter::space(self.fields)
+    }
+
+    /// The layout stored in accounts of `len` bytes, if any
+    pub fn find(layouts: &[CounterLayout], len: usize) -> Option<CounterLayout> {
+        layouts.iter().copied().find(|layout| layout.space() == len)
+    }
+}
+
+/// Reads `Counter` fields in declaration order from an older layout,
+/// returning defaults once the layout's fields run out
+struct LayoutReader<'a> {
+    data: &'a [u8],
+    fields: usize,
+}
+
+impl LayoutReader<'_> {
+    /// Read the next field, or its default if the layout predates it
+    fn read<T: AnchorDeserialize + Default>(&mut self) -> Result<T> {
+        self.read_or(T::default())
+    }
+
+    /// Read the next field, or `default` if the layout predates it
+    fn read_or<T: AnchorDeserialize>(&mut self, default: T) -> Result<T> {
+        if self.fields == 0 {
+            return Ok(default);
+        }
+        self.fields -= 1;
+        Ok(T::deserialize(&mut self.data)?)
+    }
+}
+
+/// Append `entries` to the counter's history account, the first of
+/// `remaining_accounts`, if the counter has one
+fn record_history<'info>(
+    counter: &Account<'info, Counter>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    entries: &[HistoryEntry],
+) -> Result<()> {
+    if !counter.has_history {
+        return Ok(());
+    }
+    let info = remaining_accounts
+        .first()
+        .ok_or(CounterError::InvalidHistoryAccount)?;
+    let loader = AccountLoader::<History>::try_from(info)?;
+    let mut history = loader.load_mut()?;
+    require_keys_eq!(
+        history.counter,
+        counter.key(),
+        CounterError::InvalidHistoryAccount
+    );
+
+    for entry in entries {
+        history.push(*entry);
+    }
+    Ok(())
+}
+
+/// Fail with `RateLimited` unless the current slot is past every
+/// `(last_slot, cooldown)` pair, skipping pairs where either is 0
+fn check_cooldowns(cooldowns: &[(u64, u64)]) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let next_slot = cooldowns
+        .iter()
+        .filter(|&&(last_slot, cooldown)| last_slot != 0 && cooldown != 0)
+        .map(|&(last_slot, cooldown)| last_slot.saturating_add(cooldown))
+        .max();
+
+    if let Some(next_slot) = next_slot {
+        if slot < next_slot {
+            msg!("Rate limited: next update allowed at slot {}", next_slot);
+            return err!(CounterError::RateLimited);
+        }
+    }
+    Ok(())
+}
+
+/// Close a program-owned account that cannot be loaded as `Account<Counter>`,
+/// sending its lamports to `destination`
+fn close_account<'info>(info: &AccountInfo<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(info.lamports())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **info.lamports.borrow_mut() = 0;
+
+    info.assign(&System::id());
+    info.realloc(0, false).map_err(Into::into)
+}
+
+/// Fail with `InvalidPermit` unless the instruction before the current one
+/// is an Ed25519 signature check of `message` by `signer`.
+///
+/// The signature program fails the whole transaction on a bad signature, so
+/// only the key and message it checked are compared here. Both must live in
+/// the check's own instruction data (instruction index `u16::MAX`).
+fn verify_ed25519(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = instructions_sysvar::load_current_index_checked(instructions)?;
+    let previous = current.checked_sub(1).ok_or(CounterError::InvalidPermit)?;
+    let check = instructions_sysvar::load_instruction_at_checked(previous.into(), instructions)?;
+    require_keys_eq!(
+        check.program_id,
+        ed25519_program::ID,
+        CounterError::InvalidPermit
+    );
+
+    // 1 (signature count) + 1 (padding) + 7 u16 offsets for the one signature
+    let data = &check.data;
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        CounterError::InvalidPermit
+    );
+    let offset = |at: usize| usize::from(u16::from_le_bytes([data[at], data[at + 1]]));
+    let this_instruction = usize::from(u16::MAX);
+    require!(
+        offset(8) == this_instruction && offset(14) == this_instruction,
+        CounterError::InvalidPermit
+    );
+
+    let public_key = data.get(offset(6)..offset(6) + 32);
+    let signed = data.get(offset(10)..offset(10) + offset(12));
+    require!(
+        public_key == Some(signer.as_ref()) && signed == Some(message),
+        CounterError::InvalidPermit
+    );
+    Ok(())
+}
+
+/// Context for initializing a new counter
+#[derive(Accounts)]
+#[instruction(counter_id: u64)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Counter::LEN,
+        seeds = [b"counter", authority.key().as_ref(), &counter_id.to_le_bytes()],
+        bump
+    )]
+    pub counter: Account<'info, Counter>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Mint the per-operation fee is charged in; only for counters with a fee
+    pub fee_mint: Option<Account<'info, Mint>>,
+
+    /// Vault the fees are collected in, owned by the counter
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"fee_vault", counter.key().as_ref()],
+        bump,
+        token::mint = fee_mint,
+        token::authority = counter
+    )]
+    pub fee_vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+/// Context for incrementing or decrementing, open to the authority and operators
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct Operate<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        constraint = counter.is_operator(&operator.key()) @ CounterError::NotOperator,
+        constraint = !counter.paused @ CounterError::Paused
+    )]
+    pub counter: Account<'info, Counter>,
+
+    /// The authority or one of its operators; pays for its contribution record
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    /// CHECK: the operator's contribution record, created by the handler on
+    /// their first operation. Anchor creates `init` accounts before it checks
+    /// `counter`, so letting it do so would charge non-operators for a record
+    /// and fail them with a system program error instead of `NotOperator`.
+    #[account(
+        mut,
+        seeds = [b"contribution", counter.key().as_ref(), operator.key().as_ref()],
+        bump
+    )]
+    pub contribution: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Operator's token account the fee is paid from; only for counters with a fee
+    #[account(mut)]
+    pub fee_source: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub fee_vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Operator's token account holding the gate mint, or an NFT of the
+    /// gate collection; only for gated counters
+    pub gate_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Metaplex metadata of the NFT in `gate_token_account`; only for
+    /// counters gated on a collection
+    pub gate_metadata: Option<Account<'info, MetadataAccount>>,
+
+    /// Mint milestone rewards are paid in; only for counters with milestones
+    #[account(mut)]
+    pub reward_mint: Option<Account<'info, Mint>>,
+
+    /// Operator's associated token account for the reward mint
+    #[account(mut)]
+    pub reward_account: Option<Account<'info, TokenAccount>>,
+}
+
+impl<'info> Operate<'info> {
+    /// Fail with `RateLimited` if the counter, or the operator on it, was
+    /// updated within its cooldown
+    fn check_cooldown(&self) -> Result<()> {
+        let last_slot = self.contribution()?.map_or(0, |record| record.last_slot);
+        self.counter.check_cooldown(last_slot)
+    }
+
+    /// The operator's contribution record, or `None` before their first
+    /// operation
+    fn contribution(&self) -> Result<Option<Contribution>> {
+        let info = self.contribution.to_account_info();
+        if *info.owner != crate::ID {
+            return Ok(None);
+        }
+        let contribution = Contribution::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        Ok(Some(contribution))
+    }
+
+    /// Fail with `GateNotSatisfied` unless the operator holds enough of the
+    /// gate mint, or an NFT of the gate collection, if the counter is gated
+    fn check_gate(&self) -> Result<()> {
+        let satisfied = match (self.counter.gate_mint, self.counter.gate_collection) {
+            (Some(gate_mint), _) => self.gate_token_account.as_ref().is_some_and(|account| {
+                account.mint == gate_mint
+                    && account.owner == self.operator.key()
+                    && account.amount >= self.counter.min_balance
+            }),
+            (None, Some(collection)) => self.holds_collection_item(&collection),
+            (None, None) => return Ok(()),
+        };
+        require!(satisfied, CounterError::GateNotSatisfied);
+        Ok(())
+    }
+
+    /// Whether the operator's gate token account holds an NFT whose metadata
+    /// places it in the verified `collection`
+    fn holds_collection_item(&self, collection: &Pubkey) -> bool {
+        let (Some(account), Some(metadata)) = (
+            self.gate_token_account.as_ref(),
+            self.gate_metadata.as_ref(),
+        ) else {
+            return false;
+        };
+        account.owner == self.operator.key()
+            && account.amount > 0
+            && metadata.mint == account.mint
+            && metadata
+                .collection
+                .as_ref()
+                .is_some_and(|item| item.verified && item.key == *collection)
+    }
+
+    /// Count the operation on the operator's contribution record, creating
+    /// it at the operator's expense on their first operation
+    fn record_contribution(&mut self, bump: u8, increment: bool) -> Result<()> {
+        let mut contribution = match self.contribution()? {
+            Some(contribution) => contribution,
+            None => {
+                self.create_contribution(bump)?;
+                Contribution {
+                    counter: self.counter.key(),
+                    user: self.operator.key(),
+                    increments: 0,
+                    decrements: 0,
+                    last_slot: 0,
+                    bump,
+                }
+            }
+        };
+        if increment {
+            contribution.increments = contribution.increments.saturating_add(1);
+        } else {
+            contribution.decrements = contribution.decrements.saturating_add(1);
+        }
+        contribution.last_slot = Clock::get()?.slot;
+
+        let info = self.contribution.to_account_info();
+        contribution.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    /// Allocate the contribution record and hand it to the program. The
+    /// operator tops its lamports up to rent exemption, which also covers
+    /// an address someone sent lamports to beforehand.
+    fn create_contribution(&self, bump: u8) -> Result<()> {
+        let counter = self.counter.key();
+        let operator = self.operator.key();
+        let seeds: &[&[u8]] = &[
+            b"contribution",
+            counter.as_ref(),
+            operator.as_ref(),
+            &[bump],
+        ];
+        let system_program = self.system_program.to_account_info();
+        let info = self.contribution.to_account_info();
+
+        let top_up = Rent::get()?
+            .minimum_balance(Contribution::LEN)
+            .saturating_sub(info.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.operator.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        anchor_lang::system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Allocate {
+                    account_to_allocate: info.clone(),
+                },
+                &[seeds],
+            ),
+            Contribution::LEN as u64,
+        )?;
+        anchor_lang::system_program::assign(
+            CpiContext::new_with_signer(
+                system_program,
+                anchor_lang::system_program::Assign {
+                    account_to_assign: info,
+                },
+                &[seeds],
+            ),
+            &crate::ID,
+        )
+    }
+
+    /// Mint the rewards of `milestones` to the operator's associated token
+    /// account for the reward mint
+    fn mint_rewards(&self, milestones: &[Milestone]) -> Result<()> {
+        if milestones.is_empty() {
+            return Ok(());
+        }
+        let (Some(mint), Some(destination), Some(token_program)) =
+            (&self.reward_mint, &self.reward_account, &self.token_program)
+        else {
+            return err!(CounterError::InvalidRewardAccounts);
+        };
+
+        require!(
+            self.counter.reward_mint == Some(mint.key()),
+            CounterError::InvalidRewardAccounts
+        );
+        require_keys_eq!(
+            destination.key(),
+            get_associated_token_address(&self.operator.key(), &mint.key()),
+            CounterError::InvalidRewardAccounts
+        );
+
+        let amount = milestones
+            .iter()
+            .try_fold(0u64, |total, milestone| total.checked_add(milestone.reward))
+            .ok_or(CounterError::Overflow)?;
+        let counter_id = self.counter.counter_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"counter",
+            self.counter.creator.as_ref(),
+            &counter_id,
+            &[self.counter.bump],
+        ];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                MintTo {
+                    mint: mint.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: self.counter.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )
+    }
+
+    /// Transfer the counter's fee, if it charges one, from the operator to the vault
+    fn charge_fee(&self) -> Result<()> {
+        let Some(fee) = self.counter.fee else {
+            return Ok(());
+        };
+        let (Some(source), Some(vault), Some(token_program)) =
+            (&self.fee_source, &self.fee_vault, &self.token_program)
+        else {
+            return err!(CounterError::InvalidFeeAccounts);
+        };
+
+        require_keys_eq!(vault.key(), fee.vault, CounterError::InvalidFeeAccounts);
+        require_keys_eq!(source.mint, fee.mint, CounterError::WrongFeeMint);
+        require!(
+            source.amount >= fee.amount,
+            CounterError::InsufficientBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                token_program.to_account_info(),
+                Transfer {
+                    from: source.to_account_info(),
+                    to: vault.to_account_info(),
+                    authority: self.operator.to_account_info(),
+                },
+            ),
+            fee.amount,
+        )
+    }
+}
+
+/// Context for submitting an increment permit; no signer is required
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct IncrementWithPermit<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        constraint = !counter.paused @ CounterError::Paused
+    )]
+    pub counter: Account<'info, Counter>,
+
+    /// CHECK: the instructions sysvar, checked by address
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+/// Context for authority-only updates to the count (reset, compare-and-set,
+/// batches)
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct Update<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized,
+        constraint = !counter.paused @ CounterError::Paused
+    )]
+    pub counter: Account<'info, Counter>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Context for authority-only instructions (authority changes, configuration)
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct UpdateAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized
+    )]
+    pub counter: Account<'info, Counter>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Context for registering milestone rewards
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct SetMilestones<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized
+    )]
+    pub counter: Account<'info, Counter>,
+
+    pub authority: Signer<'info>,
+
+    /// Mint the rewards are paid in, with the counter as its mint authority;
+    /// only when milestones are set
+    pub reward_mint: Option<Account<'info, Mint>>,
+}
+
+/// Context for creating a counter's history account
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct InitHistory<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized
+    )]
+    pub counter: Account<'info, Counter>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = History::LEN,
+        seeds = [b"history", counter.key().as_ref()],
+        bump
+    )]
+    pub history: AccountLoader<'info, History>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
-        counter.authority = new_authority;
+    pub system_program: Program<'info, System>,
+}
 
-        msg!("Authority updated from {} to {}", old_authority, new_authority);
+/// Context for closing a counter's history account
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct CloseHistory<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized
+    )]
+    pub counter: Account<'info, Counter>,
 
-        emit!(AuthorityUpdated {
-            counter: ctx.accounts.counter.key(),
-            old_authority,
-            new_authority,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+    #[account(
+        mut,
+        seeds = [b"history", counter.key().as_ref()],
+        bump,
+        close = authority
+    )]
+    pub history: AccountLoader<'info, History>,
 
-        Ok(())
-    }
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
-/// Account structure for the counter
-#[account]
-pub struct Counter {
-    /// Current count value
-    pub count: u64,
-    /// Authority that can modify the counter
-    pub authority: Pubkey,
-    /// Bump seed for PDA
-    pub bump: u8,
-}
+/// Context for accepting a proposed authority
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump
+    )]
+    pub counter: Account<'info, Counter>,
 
-impl Counter {
-    /// Space required for the Counter account
-    /// 8 (discriminator) + 8 (count) + 32 (authority) + 1 (bump)
-    pub const LEN: usize = 8 + 8 + 32 + 1;
+    pub pending_authority: Signer<'info>,
 }
 
-/// Context for initializing a new counter
+/// Context for closing a counter
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct CloseCounter<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = Counter::LEN,
-        seeds = [b"counter", authority.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
+        bump = counter.bump,
+        has_one = authority @ CounterError::Unauthorized,
+        close = authority
     )]
     pub counter: Account<'info, Counter>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    pub system_program: Program<'info, System>,
+    /// The counter's fee vault; only for counters with a fee
+    #[account(mut)]
+    pub fee_vault: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+impl<'info> CloseCounter<'info> {
+    /// Close the fee vault, if the counter has one, returning its rent to the authority
+    fn close_fee_vault(&self) -> Result<()> {
+        let Some(fee) = self.counter.fee else {
+            return Ok(());
+        };
+        let (Some(vault), Some(token_program)) = (&self.fee_vault, &self.token_program) else {
+            return err!(CounterError::InvalidFeeAccounts);
+        };
+
+        require_keys_eq!(vault.key(), fee.vault, CounterError::InvalidFeeAccounts);
+        require!(vault.amount == 0, CounterError::FeeVaultNotEmpty);
+
+        let counter_id = self.counter.counter_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"counter",
+            self.counter.creator.as_ref(),
+            &counter_id,
+            &[self.counter.bump],
+        ];
+        token::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            CloseAccount {
+                account: vault.to_account_info(),
+                destination: self.authority.to_account_info(),
+                authority: self.counter.to_account_info(),
+            },
+            &[seeds],
+        ))
+    }
 }
 
-/// Context for updating the counter
+/// Context for closing one's own contribution record
 #[derive(Accounts)]
-pub struct Update<'info> {
+pub struct CloseContribution<'info> {
     #[account(
         mut,
-        seeds = [b"counter", authority.key().as_ref()],
+        seeds = [b"contribution", contribution.counter.as_ref(), contribution.user.as_ref()],
+        bump = contribution.bump,
+        has_one = user @ CounterError::Unauthorized,
+        close = user
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    /// CHECK: the record's counter, which may already be closed or still
+    /// need migrating; its cooldown is checked in the handler
+    #[account(address = contribution.counter)]
+    pub counter: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Context for withdrawing collected fees
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [b"counter", counter.creator.as_ref(), &counter.counter_id.to_le_bytes()],
         bump = counter.bump,
         has_one = authority @ CounterError::Unauthorized
     )]
     pub counter: Account<'info, Counter>,
 
     pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = counter.fee.map(|fee| fee.vault) == Some(fee_vault.key())
+            @ CounterError::InvalidFeeAccounts
+    )]
+    pub fee_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination.mint == fee_vault.mint @ CounterError::WrongFeeMint
+    )]
+    pub destination: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
 }
 
-/// Context for updating authority
+/// Context for upgrading a counter to the current layout in place
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
-pub struct UpdateAuthority<'info> {
+pub struct Migrate<'info> {
+    /// CHECK: older layouts cannot be loaded as `Account<Counter>`; the owner,
+    /// discriminator, size and address are validated in the handler
+    #[account(mut)]
+    pub counter: UncheckedAccount<'info>,
+
+    /// Tops up the rent of the reallocated account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for moving a legacy counter to its id-based address
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(creator: Pubkey, counter_id: u64)]
+pub struct MigrateCounter<'info> {
+    /// CHECK: older layouts cannot be loaded as `Account<Counter>`; the owner,
+    /// discriminator and size are validated in the handler
     #[account(
         mut,
-        seeds = [b"counter", authority.key().as_ref()],
-        bump = counter.bump,
-        has_one = authority @ CounterError::Unauthorized
+        seeds = [b"counter", creator.as_ref()],
+        bump
+    )]
+    pub legacy_counter: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Counter::LEN,
+        seeds = [b"counter", creator.as_ref(), &counter_id.to_le_bytes()],
+        bump
     )]
     pub counter: Account<'info, Counter>,
 
+    #[account(mut)]
     pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 /// Events
 #[event]
+#[derive(Debug, Clone)]
 pub struct CounterUpdated {
     pub counter: Pubkey,
+    pub previous_value: u64,
+    pub new_value: u64,
+    /// Signed change applied to the count
+    pub delta: i128,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterOpsApplied {
+    pub counter: Pubkey,
+    pub previous_value: u64,
     pub new_value: u64,
+    /// Number of ops in the batch
+    pub ops: u32,
     pub timestamp: i64,
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct CounterReset {
     pub counter: Pubkey,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct AuthorityUpdated {
+#[derive(Debug, Clone)]
+pub struct ResetScheduled {
+    pub counter: Pubkey,
+    /// Unix timestamp from which the reset can be executed
+    pub unlock_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct ResetCancelled {
+    pub counter: Pubkey,
+    pub unlock_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct ResetLockConfigured {
+    pub counter: Pubkey,
+    pub require_scheduled_reset: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterPaused {
+    pub counter: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterUnpaused {
+    pub counter: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct OperatorAdded {
+    pub counter: Pubkey,
+    pub operator: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct OperatorRemoved {
+    pub counter: Pubkey,
+    pub operator: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterConfigured {
+    pub counter: Pubkey,
+    pub min: u64,
+    pub max: u64,
+    pub overflow_policy: OverflowPolicy,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct GateConfigured {
+    pub counter: Pubkey,
+    pub gate_mint: Option<Pubkey>,
+    pub min_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CollectionGateConfigured {
+    pub counter: Pubkey,
+    pub collection: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CooldownConfigured {
+    pub counter: Pubkey,
+    pub cooldown_slots: u64,
+    pub caller_cooldown_slots: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct WindowConfigured {
+    pub counter: Pubkey,
+    pub window: Option<RollingWindow>,
+    /// Index of the window the count now belongs to
+    pub window_index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct WindowClosed {
+    pub counter: Pubkey,
+    /// Index of the window that closed
+    pub window_index: u64,
+    /// Count at the close of the window, archived in `previous_window_total`
+    pub total: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct MilestonesConfigured {
+    pub counter: Pubkey,
+    pub reward_mint: Option<Pubkey>,
+    pub milestones: Vec<Milestone>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct MilestoneReached {
+    pub counter: Pubkey,
+    /// Caller whose increment reached the milestone and received the reward
+    pub recipient: Pubkey,
+    pub threshold: u64,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct HistoryInitialized {
+    pub counter: Pubkey,
+    pub history: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct HistoryClosed {
+    pub counter: Pubkey,
+    pub history: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct AuthorityProposed {
+    pub counter: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct AuthorityAccepted {
     pub counter: Pubkey,
     pub old_authority: Pubkey,
     pub new_authority: Pubkey,
     pub timestamp: i64,
 }
 
-/// Custom errors
+#[event]
+#[derive(Debug, Clone)]
+pub struct AuthorityTransferCancelled {
+    pub counter: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct FeesWithdrawn {
+    pub counter: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterClosed {
+    pub counter: Pubkey,
+    pub authority: Pubkey,
+    pub final_value: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterUpgraded {
+    pub counter: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CounterMigrated {
+    pub legacy_counter: Pubkey,
+    pub counter: Pubkey,
+    pub creator: Pubkey,
+    pub counter_id: u64,
+    pub timestamp: i64,
+}
+
+/// Custom errors. `TryFrom<u32>` maps a discriminant, the error code less
+/// `ERROR_CODE_OFFSET`, back to its variant.
 #[error_code]
+#[derive(TryFromPrimitive)]
 pub enum CounterError {
     #[msg("Counter overflow: maximum value reached")]
     Overflow,
 
-    #[msg("Counter underflow: cannot decrement below zero")]
+    #[msg("Counter underflow: cannot decrement below the minimum")]
     Underflow,
 
     #[msg("Unauthorized: only the authority can perform this action")]
     Unauthorized,
+
+    #[msg("Account is not a legacy counter awaiting migration")]
+    InvalidLegacyAccount,
+
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+
+    #[msg("Invalid bounds: min must not exceed max")]
+    InvalidBounds,
+
+    #[msg("Counter value is outside the configured bounds")]
+    OutOfBounds,
+
+    #[msg("Counter is paused")]
+    Paused,
+
+    #[msg("Counter is not paused")]
+    NotPaused,
+
+    #[msg("Stale value: counter no longer holds the expected value")]
+    StaleValue,
+
+    #[msg("Unauthorized: only the authority or an operator can perform this action")]
+    NotOperator,
+
+    #[msg("Operator is already registered")]
+    OperatorAlreadyAdded,
+
+    #[msg("Operator limit reached")]
+    TooManyOperators,
+
+    #[msg("Operator is not registered")]
+    OperatorNotFound,
+
+    #[msg("Fee accounts must be supplied exactly when the counter charges a fee")]
+    InvalidFeeAccounts,
+
+    #[msg("Token account does not hold the counter's fee mint")]
+    WrongFeeMint,
+
+    #[msg("Insufficient token balance")]
+    InsufficientBalance,
+
+    #[msg("Fee vault must be emptied before the counter is closed")]
+    FeeVaultNotEmpty,
+
+    #[msg("Signer lacks the gate mint balance or a verified collection NFT")]
+    GateNotSatisfied,
+
+    #[msg("Rate limited: the cooldown since the last update has not passed")]
+    RateLimited,
+
+    #[msg("Permit is not signed by the counter's authority")]
+    InvalidPermit,
+
+    #[msg("Permit has expired")]
+    PermitExpired,
+
+    #[msg("Permit nonce does not match the counter's next nonce")]
+    InvalidPermitNonce,
+
+    #[msg("A reset is already scheduled")]
+    ResetAlreadyScheduled,
+
+    #[msg("No reset is scheduled")]
+    NoPendingReset,
+
+    #[msg("Scheduled reset is still time-locked")]
+    ResetLocked,
+
+    #[msg("Scheduled reset's execution window has closed")]
+    ResetWindowClosed,
+
+    #[msg("Window length must be greater than zero")]
+    InvalidWindow,
+
+    #[msg("Milestone limit reached")]
+    TooManyMilestones,
+
+    #[msg("Milestone thresholds must be strictly increasing")]
+    InvalidMilestones,
+
+    #[msg("Reward mint must be supplied and have the counter as its mint authority")]
+    InvalidRewardMint,
+
+    #[msg("Reward mint and the caller's associated token account must be supplied")]
+    InvalidRewardAccounts,
+
+    #[msg("The counter's history account must be the first remaining account")]
+    InvalidHistoryAccount,
+
+    #[msg("History account must be closed before the counter is closed")]
+    HistoryOpen,
+
+    #[msg("Resets on this counter must be scheduled")]
+    ScheduledResetRequired,
 }
@@ -0,0 +1,339 @@
+mod common;
+
+use common::{create_counter, execute, expect_error, fetch, funded_keypair, setup};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    accept_authority, add_operator, cancel_authority_transfer, close, compare_and_set, configure,
+    contribution_address, decrement, decrement_by, increment, increment_by, pause,
+    propose_authority, remove_operator, reset, unpause,
+};
+use counter_program::{Counter, CounterConfig, CounterError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+#[tokio::test]
+async fn unauthorized_signers_are_rejected() {
+    let mut context = setup().await;
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let stranger = Keypair::new();
+    let key = stranger.pubkey();
+
+    for instruction in [
+        increment(&counter, &key, None),
+        decrement(&counter, &key, None),
+        increment_by(&counter, &key, 1),
+        decrement_by(&counter, &key, 1),
+    ] {
+        expect_error(
+            &mut context,
+            instruction,
+            &[&stranger],
+            CounterError::NotOperator,
+        )
+        .await;
+    }
+    // Rejected before a contribution record is created at their expense
+    assert!(context
+        .banks_client
+        .get_account(contribution_address(&counter, &key).0)
+        .await
+        .unwrap()
+        .is_none());
+
+    for instruction in [
+        reset(&counter, &key),
+        compare_and_set(&counter, &key, 0, 1),
+        pause(&counter, &key),
+        unpause(&counter, &key),
+        configure(&counter, &key, CounterConfig::default()),
+        add_operator(&counter, &key, key),
+        remove_operator(&counter, &key, key),
+        propose_authority(&counter, &key, key),
+        cancel_authority_transfer(&counter, &key),
+        close(&counter, &key),
+    ] {
+        expect_error(
+            &mut context,
+            instruction,
+            &[&stranger],
+            CounterError::Unauthorized,
+        )
+        .await;
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 0);
+    assert_eq!(stored.authority, context.payer.pubkey());
+    assert!(stored.operators.is_empty());
+}
+
+#[tokio::test]
+async fn accepted_authority_takes_over_control() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let new_authority = funded_keypair(&mut context).await;
+
+    execute(
+        &mut context,
+        propose_authority(&counter, &payer, new_authority.pubkey()),
+        &[],
+    )
+    .await;
+    match execute(
+        &mut context,
+        accept_authority(&counter, &new_authority.pubkey()),
+        &[&new_authority],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::AuthorityAccepted(accepted)] => {
+            assert_eq!(accepted.counter, counter);
+            assert_eq!(accepted.old_authority, payer);
+            assert_eq!(accepted.new_authority, new_authority.pubkey());
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, None),
+        &[],
+        CounterError::NotOperator,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        reset(&counter, &payer),
+        &[],
+        CounterError::Unauthorized,
+    )
+    .await;
+
+    let authority = new_authority.pubkey();
+    execute(
+        &mut context,
+        increment(&counter, &authority, None),
+        &[&new_authority],
+    )
+    .await;
+    execute(&mut context, reset(&counter, &authority), &[&new_authority]).await;
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.authority, authority);
+    assert_eq!(stored.creator, payer);
+}
+
+#[tokio::test]
+async fn proposed_authority_must_accept() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let pending = Keypair::new();
+    let stranger = Keypair::new();
+
+    match execute(
+        &mut context,
+        propose_authority(&counter, &payer, pending.pubkey()),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::AuthorityProposed(proposed)] => {
+            assert_eq!(proposed.authority, payer);
+            assert_eq!(proposed.pending_authority, pending.pubkey());
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert_eq!(
+        fetch(&mut context, &counter).await.pending_authority,
+        Some(pending.pubkey())
+    );
+
+    expect_error(
+        &mut context,
+        accept_authority(&counter, &stranger.pubkey()),
+        &[&stranger],
+        CounterError::NotPendingAuthority,
+    )
+    .await;
+
+    match execute(
+        &mut context,
+        accept_authority(&counter, &pending.pubkey()),
+        &[&pending],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::AuthorityAccepted(accepted)] => {
+            assert_eq!(accepted.old_authority, payer);
+            assert_eq!(accepted.new_authority, pending.pubkey());
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.authority, pending.pubkey());
+    assert_eq!(stored.pending_authority, None);
+    expect_error(
+        &mut context,
+        pause(&counter, &payer),
+        &[],
+        CounterError::Unauthorized,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cancelled_transfer_cannot_be_accepted() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let pending = Keypair::new();
+
+    expect_error(
+        &mut context,
+        cancel_authority_transfer(&counter, &payer),
+        &[],
+        CounterError::NoPendingAuthority,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        accept_authority(&counter, &pending.pubkey()),
+        &[&pending],
+        CounterError::NoPendingAuthority,
+    )
+    .await;
+
+    execute(
+        &mut context,
+        propose_authority(&counter, &payer, pending.pubkey()),
+        &[],
+    )
+    .await;
+    match execute(
+        &mut context,
+        cancel_authority_transfer(&counter, &payer),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::AuthorityTransferCancelled(cancelled)] => {
+            assert_eq!(cancelled.authority, payer);
+            assert_eq!(cancelled.pending_authority, pending.pubkey());
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    expect_error(
+        &mut context,
+        accept_authority(&counter, &pending.pubkey()),
+        &[&pending],
+        CounterError::NoPendingAuthority,
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.authority, payer);
+}
+
+#[tokio::test]
+async fn operators_can_only_increment_and_decrement() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let operator = funded_keypair(&mut context).await;
+    let key = operator.pubkey();
+
+    match execute(&mut context, add_operator(&counter, &payer, key), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::OperatorAdded(added)] => assert_eq!(added.operator, key),
+        events => panic!("unexpected events: {events:?}"),
+    }
+    expect_error(
+        &mut context,
+        add_operator(&counter, &payer, key),
+        &[],
+        CounterError::OperatorAlreadyAdded,
+    )
+    .await;
+
+    execute(&mut context, increment_by(&counter, &key, 3), &[&operator]).await;
+    execute(
+        &mut context,
+        decrement(&counter, &key, Some(3)),
+        &[&operator],
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 2);
+
+    expect_error(
+        &mut context,
+        reset(&counter, &key),
+        &[&operator],
+        CounterError::Unauthorized,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        add_operator(&counter, &key, Pubkey::new_unique()),
+        &[&operator],
+        CounterError::Unauthorized,
+    )
+    .await;
+
+    match execute(&mut context, remove_operator(&counter, &payer, key), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::OperatorRemoved(removed)] => assert_eq!(removed.operator, key),
+        events => panic!("unexpected events: {events:?}"),
+    }
+    expect_error(
+        &mut context,
+        increment(&counter, &key, None),
+        &[&operator],
+        CounterError::NotOperator,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        remove_operator(&counter, &payer, key),
+        &[],
+        CounterError::OperatorNotFound,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn operator_list_is_capped() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    for _ in 0..Counter::MAX_OPERATORS {
+        execute(
+            &mut context,
+            add_operator(&counter, &payer, Pubkey::new_unique()),
+            &[],
+        )
+        .await;
+    }
+    expect_error(
+        &mut context,
+        add_operator(&counter, &payer, Pubkey::new_unique()),
+        &[],
+        CounterError::TooManyOperators,
+    )
+    .await;
+
+    assert_eq!(
+        fetch(&mut context, &counter).await.operators.len(),
+        Counter::MAX_OPERATORS
+    );
+}
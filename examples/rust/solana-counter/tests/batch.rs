@@ -0,0 +1,145 @@
+mod common;
+
+use common::{create_counter, execute, expect_error, fetch, setup};
+use counter_client::events::CounterEvent;
+use counter_client::{apply_ops, pause};
+use counter_program::{CounterConfig, CounterError, CounterOp, OverflowPolicy};
+use solana_sdk::signature::{Keypair, Signer};
+
+fn bounded(min: u64, max: u64) -> CounterConfig {
+    CounterConfig {
+        min,
+        max,
+        overflow_policy: OverflowPolicy::Error,
+    }
+}
+
+#[tokio::test]
+async fn apply_ops_applies_every_op_in_order() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, bounded(0, 100)).await;
+    let ops = vec![
+        CounterOp::IncrementBy(10),
+        CounterOp::Increment,
+        CounterOp::DecrementBy(3),
+        CounterOp::Decrement,
+        CounterOp::Reset,
+        CounterOp::Set(40),
+        CounterOp::Increment,
+    ];
+
+    match execute(&mut context, apply_ops(&counter, &payer, ops, false), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::OpsApplied(applied)] => {
+            assert_eq!(applied.counter, counter);
+            assert_eq!(applied.previous_value, 0);
+            assert_eq!(applied.new_value, 41);
+            assert_eq!(applied.ops, 7);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert_eq!(fetch(&mut context, &counter).await.count, 41);
+}
+
+#[tokio::test]
+async fn apply_ops_can_emit_an_event_per_op() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, bounded(0, 100)).await;
+    let ops = vec![
+        CounterOp::IncrementBy(5),
+        CounterOp::Set(2),
+        CounterOp::Reset,
+    ];
+
+    let events = execute(&mut context, apply_ops(&counter, &payer, ops, true), &[]).await;
+
+    let deltas: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            CounterEvent::Updated(updated) => {
+                Some((updated.previous_value, updated.new_value, updated.delta))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deltas, [(0, 5, 5), (5, 2, -3), (2, 0, -2)]);
+    assert!(matches!(
+        events.last(),
+        Some(CounterEvent::OpsApplied(applied)) if applied.ops == 3 && applied.new_value == 0
+    ));
+}
+
+#[tokio::test]
+async fn one_failing_op_rejects_the_whole_batch() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, bounded(0, 10)).await;
+
+    expect_error(
+        &mut context,
+        apply_ops(
+            &counter,
+            &payer,
+            vec![CounterOp::IncrementBy(8), CounterOp::IncrementBy(3)],
+            true,
+        ),
+        &[],
+        CounterError::Overflow,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        apply_ops(
+            &counter,
+            &payer,
+            vec![CounterOp::Increment, CounterOp::DecrementBy(2)],
+            false,
+        ),
+        &[],
+        CounterError::Underflow,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        apply_ops(&counter, &payer, vec![CounterOp::Set(11)], false),
+        &[],
+        CounterError::OutOfBounds,
+    )
+    .await;
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn apply_ops_is_authority_only_and_respects_pause() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let stranger = Keypair::new();
+
+    expect_error(
+        &mut context,
+        apply_ops(
+            &counter,
+            &stranger.pubkey(),
+            vec![CounterOp::Increment],
+            false,
+        ),
+        &[&stranger],
+        CounterError::Unauthorized,
+    )
+    .await;
+
+    execute(&mut context, pause(&counter, &payer), &[]).await;
+    expect_error(
+        &mut context,
+        apply_ops(&counter, &payer, vec![CounterOp::Increment], false),
+        &[],
+        CounterError::Paused,
+    )
+    .await;
+}
@@ -0,0 +1,361 @@
+//! Shared setup for the counter program's integration tests.
+//!
+//! The program runs natively under `solana-program-test` through the
+//! client's `program-test` helpers, so events can be decoded from the logs,
+//! or from the program's self-CPIs when the `event-cpi` feature is enabled.
+
+#![allow(dead_code)]
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::metadata::mpl_token_metadata;
+use anchor_spl::metadata::mpl_token_metadata::accounts::Metadata;
+use anchor_spl::metadata::mpl_token_metadata::types::{Collection, Key};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::state::{Account as TokenAccount, Mint};
+use counter_client::events::{parse_inner_instructions, parse_logs, CounterEvent};
+use counter_client::program_test::{process_transaction, program_test, start, TransactionOutcome};
+use counter_client::{counter_address, fetch_counter, initialize, metadata_address, ClientError};
+use counter_program::{Counter, CounterConfig, CounterError, CounterLayout};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+
+/// Start the program with the event log shim installed
+pub async fn setup() -> ProgramTestContext {
+    start(program_test()).await
+}
+
+/// Send `instruction` with the payer paying fees and `signers` signing alongside it.
+///
+/// A fresh blockhash is used every time so identical instructions can be
+/// sent back to back.
+pub async fn send(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> TransactionOutcome {
+    send_all(context, &[instruction], signers).await
+}
+
+/// Send `instructions` as one transaction, like `send`
+pub async fn send_all(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> TransactionOutcome {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut keypairs = vec![&context.payer];
+    keypairs.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &keypairs,
+        blockhash,
+    );
+
+    process_transaction(&mut context.banks_client, transaction)
+        .await
+        .unwrap()
+}
+
+/// Send `instruction`, assert that it succeeded and return the events it emitted
+pub async fn execute(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> Vec<CounterEvent> {
+    assert_success(&send(context, instruction, signers).await)
+}
+
+/// Assert that a transaction succeeded and return the events it emitted
+pub fn assert_success(outcome: &TransactionOutcome) -> Vec<CounterEvent> {
+    if let Err(err) = &outcome.result {
+        panic!("transaction failed: {err}\n{}", outcome.logs.join("\n"));
+    }
+    events(outcome)
+}
+
+/// The events a transaction emitted: decoded from its logs, or from the
+/// self-CPIs it made when built with `event-cpi`
+pub fn events(outcome: &TransactionOutcome) -> Vec<CounterEvent> {
+    if cfg!(feature = "event-cpi") {
+        parse_inner_instructions(
+            outcome
+                .inner_instructions
+                .iter()
+                .map(|instruction| (&instruction.program_id, instruction.data.as_slice())),
+        )
+    } else {
+        parse_logs(&outcome.logs)
+    }
+}
+
+/// Send `instruction` and assert that the program rejected it with `expected`
+pub async fn expect_error(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+    expected: CounterError,
+) {
+    assert_error(&send(context, instruction, signers).await, expected);
+}
+
+/// Assert that the program rejected a transaction with `expected`
+pub fn assert_error(outcome: &TransactionOutcome, expected: CounterError) {
+    match &outcome.result {
+        Err(ClientError::Program(err)) if u32::from(*err) == u32::from(expected) => {}
+        result => panic!("expected {expected:?}, got {result:?}"),
+    }
+    assert!(
+        events(outcome).is_empty(),
+        "a failed transaction must not emit events"
+    );
+}
+
+/// Create counter `counter_id` owned by the payer and return its address
+pub async fn create_counter(
+    context: &mut ProgramTestContext,
+    counter_id: u64,
+    config: CounterConfig,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    execute(context, initialize(&payer, counter_id, config), &[]).await;
+    counter_address(&payer, counter_id).0
+}
+
+/// Fetch and decode the counter at `address`
+pub async fn fetch(context: &mut ProgramTestContext, address: &Pubkey) -> Counter {
+    fetch_counter(&mut context.banks_client, address)
+        .await
+        .unwrap()
+}
+
+/// Account data for `counter` in an older `layout`: the discriminator and
+/// the fields the layout stores, zero-padded to the layout's size
+pub fn layout_data(counter: &Counter, layout: CounterLayout) -> Vec<u8> {
+    let fields = [
+        counter.count.try_to_vec(),
+        counter.authority.try_to_vec(),
+        counter.bump.try_to_vec(),
+        counter.creator.try_to_vec(),
+        counter.pending_authority.try_to_vec(),
+        counter.min.try_to_vec(),
+        counter.max.try_to_vec(),
+        counter.overflow_policy.try_to_vec(),
+        counter.counter_id.try_to_vec(),
+        counter.paused.try_to_vec(),
+        counter.operators.try_to_vec(),
+        counter.fee.try_to_vec(),
+        counter.gate_mint.try_to_vec(),
+        counter.min_balance.try_to_vec(),
+        counter.cooldown_slots.try_to_vec(),
+        counter.caller_cooldown_slots.try_to_vec(),
+        counter.last_slot.try_to_vec(),
+        layout.version.try_to_vec(),
+        counter.permit_nonce.try_to_vec(),
+        counter.pending_reset_at.try_to_vec(),
+        counter.window.try_to_vec(),
+        counter.window_index.try_to_vec(),
+        counter.previous_window_total.try_to_vec(),
+        counter.reward_mint.try_to_vec(),
+        counter.milestones.try_to_vec(),
+        counter.has_history.try_to_vec(),
+        counter.require_scheduled_reset.try_to_vec(),
+        counter.gate_collection.try_to_vec(),
+    ];
+
+    let mut data = Counter::DISCRIMINATOR.to_vec();
+    for field in fields.into_iter().take(layout.fields) {
+        data.extend(field.unwrap());
+    }
+    data.resize(layout.space(), 0);
+    data
+}
+
+/// Store `data` at `address` in a rent-exempt account owned by `owner`
+pub fn store_account(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+    data: &[u8],
+    owner: &Pubkey,
+) {
+    let mut account = AccountSharedData::new(
+        Rent::default().minimum_balance(data.len()),
+        data.len(),
+        owner,
+    );
+    account.set_data_from_slice(data);
+    context.set_account(address, &account);
+}
+
+/// The cluster clock as the program sees it
+pub async fn clock(context: &mut ProgramTestContext) -> Clock {
+    context.banks_client.get_sysvar().await.unwrap()
+}
+
+/// Move the cluster clock forward by `secs` seconds
+pub async fn advance_clock(context: &mut ProgramTestContext, secs: i64) {
+    let mut clock = clock(context).await;
+    clock.unix_timestamp += secs;
+    context.set_sysvar(&clock);
+}
+
+/// Move the cluster clock forward by `epochs` epochs
+pub async fn advance_epochs(context: &mut ProgramTestContext, epochs: u64) {
+    let mut clock = clock(context).await;
+    clock.epoch += epochs;
+    context.set_sysvar(&clock);
+}
+
+/// Advance the bank by `slots` slots
+pub async fn advance_slots(context: &mut ProgramTestContext, slots: u64) {
+    let clock = clock(context).await;
+    context.warp_to_slot(clock.slot + slots).unwrap();
+}
+
+/// A new keypair funded by the payer, so it can pay for its own accounts
+pub async fn funded_keypair(context: &mut ProgramTestContext) -> Keypair {
+    let keypair = Keypair::new();
+    let transfer =
+        system_instruction::transfer(&context.payer.pubkey(), &keypair.pubkey(), LAMPORTS_PER_SOL);
+    execute(context, transfer, &[]).await;
+    keypair
+}
+
+/// Create a mint with no decimals, with the payer as mint authority
+pub async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let payer = context.payer.pubkey();
+    create_mint_with_authority(context, &payer).await
+}
+
+/// Create a mint with no decimals whose mint authority is `authority`
+pub async fn create_mint_with_authority(
+    context: &mut ProgramTestContext,
+    authority: &Pubkey,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let mint = Keypair::new();
+    let create = system_instruction::create_account(
+        &payer,
+        &mint.pubkey(),
+        Rent::default().minimum_balance(Mint::LEN),
+        Mint::LEN as u64,
+        &spl_token::ID,
+    );
+    let initialize =
+        spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), authority, None, 0)
+            .unwrap();
+
+    execute(context, create, &[&mint]).await;
+    execute(context, initialize, &[]).await;
+    mint.pubkey()
+}
+
+/// Create a token account for `mint` owned by `owner`, holding `amount` tokens
+pub async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let account = Keypair::new();
+    let create = system_instruction::create_account(
+        &payer,
+        &account.pubkey(),
+        Rent::default().minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN as u64,
+        &spl_token::ID,
+    );
+    let initialize =
+        spl_token::instruction::initialize_account(&spl_token::ID, &account.pubkey(), mint, owner)
+            .unwrap();
+
+    execute(context, create, &[&account]).await;
+    execute(context, initialize, &[]).await;
+    if amount > 0 {
+        let mint_to = spl_token::instruction::mint_to(
+            &spl_token::ID,
+            mint,
+            &account.pubkey(),
+            &payer,
+            &[],
+            amount,
+        )
+        .unwrap();
+        execute(context, mint_to, &[]).await;
+    }
+    account.pubkey()
+}
+
+/// Create `owner`'s associated token account for `mint`
+pub async fn create_associated_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let create = spl_associated_token_account::instruction::create_associated_token_account(
+        &payer,
+        owner,
+        mint,
+        &spl_token::ID,
+    );
+    execute(context, create, &[]).await;
+    get_associated_token_address(owner, mint)
+}
+
+/// Token balance of `account`, or `None` if it does not exist
+pub async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> Option<u64> {
+    let account = context.banks_client.get_account(*account).await.unwrap()?;
+    Some(TokenAccount::unpack(&account.data).unwrap().amount)
+}
+
+/// Store Metaplex metadata for `mint` placing it in `collection`, verified
+/// or not, and return its address. The metadata program itself is not
+/// loaded; the counter program only reads the account.
+pub fn store_metadata(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    collection: &Pubkey,
+    verified: bool,
+) -> Pubkey {
+    let metadata = Metadata {
+        key: Key::MetadataV1,
+        update_authority: context.payer.pubkey(),
+        mint: *mint,
+        name: "Counter Pass".to_string(),
+        symbol: "PASS".to_string(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection: Some(Collection {
+            verified,
+            key: *collection,
+        }),
+        uses: None,
+        collection_details: None,
+        programmable_config: None,
+    };
+    let address = metadata_address(mint).0;
+    store_account(
+        context,
+        &address,
+        &metadata.try_to_vec().unwrap(),
+        &mpl_token_metadata::ID,
+    );
+    address
+}
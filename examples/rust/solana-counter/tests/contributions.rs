@@ -0,0 +1,98 @@
+mod common;
+
+use common::{create_counter, execute, expect_error, funded_keypair, setup};
+use counter_client::{
+    add_operator, close_contribution, contribution_address, decrement, fetch_contribution,
+    increment, increment_by,
+};
+use counter_program::{Contribution, CounterConfig, CounterError};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+async fn fetch_record(
+    context: &mut ProgramTestContext,
+    counter: &Pubkey,
+    user: &Pubkey,
+) -> Contribution {
+    let (address, _) = contribution_address(counter, user);
+    fetch_contribution(&mut context.banks_client, &address)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn first_operation_creates_the_record() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+
+    let record = fetch_record(&mut context, &counter, &payer).await;
+    assert_eq!(record.counter, counter);
+    assert_eq!(record.user, payer);
+    assert_eq!((record.increments, record.decrements), (1, 0));
+    assert!(record.last_slot > 0);
+    assert_eq!(record.bump, contribution_address(&counter, &payer).1);
+}
+
+#[tokio::test]
+async fn records_are_kept_per_user() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let operator = funded_keypair(&mut context).await;
+    execute(
+        &mut context,
+        add_operator(&counter, &payer, operator.pubkey()),
+        &[],
+    )
+    .await;
+
+    execute(&mut context, increment_by(&counter, &payer, 5), &[]).await;
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    execute(
+        &mut context,
+        decrement(&counter, &operator.pubkey(), None),
+        &[&operator],
+    )
+    .await;
+
+    let record = fetch_record(&mut context, &counter, &payer).await;
+    assert_eq!((record.increments, record.decrements), (2, 0));
+    let record = fetch_record(&mut context, &counter, &operator.pubkey()).await;
+    assert_eq!((record.increments, record.decrements), (0, 1));
+}
+
+#[tokio::test]
+async fn only_the_user_closes_their_record() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let (address, _) = contribution_address(&counter, &payer);
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+
+    let stranger = funded_keypair(&mut context).await;
+    let mut ix = close_contribution(&counter, &stranger.pubkey());
+    ix.accounts[0].pubkey = address;
+    expect_error(&mut context, ix, &[&stranger], CounterError::Unauthorized).await;
+
+    let rent = context.banks_client.get_balance(address).await.unwrap();
+    let before = context.banks_client.get_balance(payer).await.unwrap();
+    execute(&mut context, close_contribution(&counter, &payer), &[]).await;
+    let after = context.banks_client.get_balance(payer).await.unwrap();
+
+    assert!(context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .is_none());
+    // The payer also paid the transaction fee
+    assert!(after > before && after <= before + rent);
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    let record = fetch_record(&mut context, &counter, &payer).await;
+    assert_eq!((record.increments, record.decrements), (1, 0));
+}
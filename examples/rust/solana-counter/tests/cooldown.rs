@@ -0,0 +1,146 @@
+mod common;
+
+use common::{
+    advance_slots, assert_error, create_counter, execute, expect_error, fetch, funded_keypair,
+    send_all, setup,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{add_operator, close_contribution, decrement, increment, set_cooldown};
+use counter_program::{CounterConfig, CounterError};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn set_cooldown_stores_the_limits() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    match execute(&mut context, set_cooldown(&counter, &payer, 3, 10), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::CooldownConfigured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert_eq!(configured.cooldown_slots, 3);
+            assert_eq!(configured.caller_cooldown_slots, 10);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(
+        (stored.cooldown_slots, stored.caller_cooldown_slots),
+        (3, 10)
+    );
+
+    let stranger = funded_keypair(&mut context).await;
+    expect_error(
+        &mut context,
+        set_cooldown(&counter, &stranger.pubkey(), 0, 0),
+        &[&stranger],
+        CounterError::Unauthorized,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn counter_cooldown_applies_to_every_signer() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let operator = funded_keypair(&mut context).await;
+    execute(
+        &mut context,
+        add_operator(&counter, &payer, operator.pubkey()),
+        &[],
+    )
+    .await;
+    execute(&mut context, set_cooldown(&counter, &payer, 5, 0), &[]).await;
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    let last_slot = fetch(&mut context, &counter).await.last_slot;
+    assert!(last_slot > 0);
+
+    expect_error(
+        &mut context,
+        decrement(&counter, &operator.pubkey(), None),
+        &[&operator],
+        CounterError::RateLimited,
+    )
+    .await;
+
+    advance_slots(&mut context, 5).await;
+    execute(
+        &mut context,
+        decrement(&counter, &operator.pubkey(), None),
+        &[&operator],
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn caller_cooldown_applies_per_signer() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let operator = funded_keypair(&mut context).await;
+    execute(
+        &mut context,
+        add_operator(&counter, &payer, operator.pubkey()),
+        &[],
+    )
+    .await;
+    execute(&mut context, set_cooldown(&counter, &payer, 0, 5), &[]).await;
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    execute(
+        &mut context,
+        increment(&counter, &operator.pubkey(), None),
+        &[&operator],
+    )
+    .await;
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, None),
+        &[],
+        CounterError::RateLimited,
+    )
+    .await;
+
+    advance_slots(&mut context, 5).await;
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 3);
+}
+
+#[tokio::test]
+async fn closing_the_contribution_does_not_skip_the_caller_cooldown() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, set_cooldown(&counter, &payer, 0, 10), &[]).await;
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+
+    let outcome = send_all(
+        &mut context,
+        &[
+            close_contribution(&counter, &payer),
+            increment(&counter, &payer, None),
+        ],
+        &[],
+    )
+    .await;
+    assert_error(&outcome, CounterError::RateLimited);
+    expect_error(
+        &mut context,
+        close_contribution(&counter, &payer),
+        &[],
+        CounterError::RateLimited,
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 1);
+
+    advance_slots(&mut context, 10).await;
+    execute(&mut context, close_contribution(&counter, &payer), &[]).await;
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 2);
+}
@@ -0,0 +1,351 @@
+mod common;
+
+use common::{create_counter, execute, expect_error, fetch, setup};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    close, compare_and_set, configure, counter_address, decrement, decrement_by, increment,
+    increment_by, initialize, pause, reset, unpause,
+};
+use counter_program::{Counter, CounterConfig, CounterError, OverflowPolicy};
+use solana_sdk::signature::Signer;
+
+fn bounded(min: u64, max: u64, overflow_policy: OverflowPolicy) -> CounterConfig {
+    CounterConfig {
+        min,
+        max,
+        overflow_policy,
+    }
+}
+
+#[tokio::test]
+async fn initialize_stores_config_and_starts_at_min() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+
+    let first = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let second = create_counter(&mut context, 1, bounded(5, 10, OverflowPolicy::Saturate)).await;
+    assert_ne!(first, second);
+
+    let counter = fetch(&mut context, &second).await;
+    assert_eq!(counter.count, 5);
+    assert_eq!(counter.authority, payer);
+    assert_eq!(counter.creator, payer);
+    assert_eq!(counter.counter_id, 1);
+    assert_eq!(counter.bump, counter_address(&payer, 1).1);
+    assert_eq!(counter.pending_authority, None);
+    assert_eq!((counter.min, counter.max), (5, 10));
+    assert_eq!(counter.overflow_policy, OverflowPolicy::Saturate);
+    assert!(!counter.paused);
+    assert!(counter.operators.is_empty());
+    assert_eq!(counter.fee, None);
+    assert_eq!((counter.gate_mint, counter.min_balance), (None, 0));
+    assert_eq!(
+        (counter.cooldown_slots, counter.caller_cooldown_slots),
+        (0, 0)
+    );
+    assert_eq!(counter.last_slot, 0);
+    assert_eq!(counter.version, Counter::VERSION);
+    assert_eq!(counter.permit_nonce, 0);
+    assert_eq!(counter.pending_reset_at, None);
+    assert_eq!(counter.window, None);
+    assert_eq!(counter.previous_window_total, 0);
+    assert_eq!(counter.reward_mint, None);
+    assert!(counter.milestones.is_empty());
+    assert!(!counter.has_history);
+
+    assert_eq!(fetch(&mut context, &first).await.count, 0);
+}
+
+#[tokio::test]
+async fn initialize_rejects_invalid_bounds() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+
+    expect_error(
+        &mut context,
+        initialize(&payer, 0, bounded(10, 5, OverflowPolicy::Error)),
+        &[],
+        CounterError::InvalidBounds,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn increments_and_decrements_emit_updates() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    let steps = [
+        (increment(&counter, &payer, None), 0, 1, 1),
+        (increment(&counter, &payer, Some(1)), 1, 2, 1),
+        (increment_by(&counter, &payer, 10), 2, 12, 10),
+        (decrement(&counter, &payer, None), 12, 11, -1),
+        (decrement_by(&counter, &payer, 4), 11, 7, -4),
+    ];
+    for (instruction, previous_value, new_value, delta) in steps {
+        match execute(&mut context, instruction, &[]).await.as_slice() {
+            [CounterEvent::Updated(updated)] => {
+                assert_eq!(updated.counter, counter);
+                assert_eq!(updated.previous_value, previous_value);
+                assert_eq!(updated.new_value, new_value);
+                assert_eq!(updated.delta, delta);
+            }
+            events => panic!("unexpected events: {events:?}"),
+        }
+    }
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 7);
+}
+
+#[tokio::test]
+async fn decrement_at_zero_underflows() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    expect_error(
+        &mut context,
+        decrement(&counter, &payer, None),
+        &[],
+        CounterError::Underflow,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        decrement_by(&counter, &payer, 1),
+        &[],
+        CounterError::Underflow,
+    )
+    .await;
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn increment_past_max_overflows() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let unbounded = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let bounded = create_counter(&mut context, 1, bounded(0, 1, OverflowPolicy::Error)).await;
+
+    execute(&mut context, increment(&unbounded, &payer, None), &[]).await;
+    expect_error(
+        &mut context,
+        increment_by(&unbounded, &payer, u64::MAX),
+        &[],
+        CounterError::Overflow,
+    )
+    .await;
+
+    execute(&mut context, increment(&bounded, &payer, None), &[]).await;
+    expect_error(
+        &mut context,
+        increment(&bounded, &payer, None),
+        &[],
+        CounterError::Overflow,
+    )
+    .await;
+
+    assert_eq!(fetch(&mut context, &unbounded).await.count, 1);
+    assert_eq!(fetch(&mut context, &bounded).await.count, 1);
+}
+
+#[tokio::test]
+async fn saturate_and_wrap_stay_within_bounds() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let saturating = create_counter(&mut context, 0, bounded(2, 5, OverflowPolicy::Saturate)).await;
+    let wrapping = create_counter(&mut context, 1, bounded(2, 5, OverflowPolicy::Wrap)).await;
+
+    execute(&mut context, increment_by(&saturating, &payer, 100), &[]).await;
+    assert_eq!(fetch(&mut context, &saturating).await.count, 5);
+    execute(&mut context, decrement_by(&saturating, &payer, 100), &[]).await;
+    assert_eq!(fetch(&mut context, &saturating).await.count, 2);
+
+    execute(&mut context, increment_by(&wrapping, &payer, 4), &[]).await;
+    assert_eq!(fetch(&mut context, &wrapping).await.count, 2);
+    execute(&mut context, decrement(&wrapping, &payer, None), &[]).await;
+    assert_eq!(fetch(&mut context, &wrapping).await.count, 5);
+}
+
+#[tokio::test]
+async fn expected_value_guards_reject_stale_updates() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, bounded(0, 10, OverflowPolicy::Error)).await;
+
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, Some(1)),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+    execute(&mut context, increment(&counter, &payer, Some(0)), &[]).await;
+    expect_error(
+        &mut context,
+        decrement(&counter, &payer, Some(0)),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+
+    match execute(&mut context, compare_and_set(&counter, &payer, 1, 7), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!(updated.previous_value, 1);
+            assert_eq!(updated.new_value, 7);
+            assert_eq!(updated.delta, 6);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    expect_error(
+        &mut context,
+        compare_and_set(&counter, &payer, 1, 2),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        compare_and_set(&counter, &payer, 7, 11),
+        &[],
+        CounterError::OutOfBounds,
+    )
+    .await;
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 7);
+}
+
+#[tokio::test]
+async fn reset_returns_to_min() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, bounded(3, 10, OverflowPolicy::Error)).await;
+
+    execute(&mut context, increment_by(&counter, &payer, 4), &[]).await;
+    match execute(&mut context, reset(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Reset(reset)] => assert_eq!(reset.counter, counter),
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 3);
+}
+
+#[tokio::test]
+async fn configure_validates_bounds_against_the_count() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, increment_by(&counter, &payer, 5), &[]).await;
+
+    expect_error(
+        &mut context,
+        configure(&counter, &payer, bounded(8, 4, OverflowPolicy::Error)),
+        &[],
+        CounterError::InvalidBounds,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        configure(&counter, &payer, bounded(6, 10, OverflowPolicy::Error)),
+        &[],
+        CounterError::OutOfBounds,
+    )
+    .await;
+
+    let config = bounded(5, 6, OverflowPolicy::Wrap);
+    match execute(&mut context, configure(&counter, &payer, config), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Configured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert_eq!((configured.min, configured.max), (5, 6));
+            assert_eq!(configured.overflow_policy, OverflowPolicy::Wrap);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!((stored.min, stored.max), (5, 6));
+    assert_eq!(stored.overflow_policy, OverflowPolicy::Wrap);
+}
+
+#[tokio::test]
+async fn pause_blocks_updates_until_unpaused() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    match execute(&mut context, pause(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Paused(paused)] => assert_eq!(paused.authority, payer),
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert!(fetch(&mut context, &counter).await.paused);
+
+    for instruction in [
+        increment(&counter, &payer, None),
+        decrement_by(&counter, &payer, 1),
+        reset(&counter, &payer),
+        compare_and_set(&counter, &payer, 0, 1),
+        pause(&counter, &payer),
+    ] {
+        expect_error(&mut context, instruction, &[], CounterError::Paused).await;
+    }
+
+    match execute(&mut context, unpause(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Unpaused(unpaused)] => assert_eq!(unpaused.authority, payer),
+        events => panic!("unexpected events: {events:?}"),
+    }
+    expect_error(
+        &mut context,
+        unpause(&counter, &payer),
+        &[],
+        CounterError::NotPaused,
+    )
+    .await;
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn close_frees_the_address() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, increment_by(&counter, &payer, 9), &[]).await;
+
+    match execute(&mut context, close(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Closed(closed)] => {
+            assert_eq!(closed.authority, payer);
+            assert_eq!(closed.final_value, 9);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert!(context
+        .banks_client
+        .get_account(counter)
+        .await
+        .unwrap()
+        .is_none());
+
+    create_counter(&mut context, 0, CounterConfig::default()).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
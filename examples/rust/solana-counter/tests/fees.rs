@@ -0,0 +1,243 @@
+mod common;
+
+use common::{
+    create_mint, create_token_account, execute, expect_error, fetch, funded_keypair, setup,
+    token_balance,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    add_operator, close, close_with_fee, counter_address, decrement_with_fee, fee_vault_address,
+    increment, increment_with_fee, initialize_with_fee, withdraw_fees,
+};
+use counter_program::{CounterConfig, CounterError, Fee};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Create counter 0 charging `fee` of a new mint and return the counter and mint
+async fn create_fee_counter(context: &mut ProgramTestContext, fee: u64) -> (Pubkey, Pubkey) {
+    let payer = context.payer.pubkey();
+    let mint = create_mint(context).await;
+    execute(
+        context,
+        initialize_with_fee(&payer, 0, CounterConfig::default(), &mint, fee),
+        &[],
+    )
+    .await;
+    (counter_address(&payer, 0).0, mint)
+}
+
+#[tokio::test]
+async fn initialize_creates_the_fee_vault() {
+    let mut context = setup().await;
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let (vault, _) = fee_vault_address(&counter);
+
+    assert_eq!(
+        fetch(&mut context, &counter).await.fee,
+        Some(Fee {
+            mint,
+            vault,
+            amount: 5,
+        })
+    );
+    assert_eq!(token_balance(&mut context, &vault).await, Some(0));
+}
+
+#[tokio::test]
+async fn operations_pay_the_fee_into_the_vault() {
+    let mut context = setup().await;
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let (vault, _) = fee_vault_address(&counter);
+    let operator = funded_keypair(&mut context).await;
+    let payer = context.payer.pubkey();
+    execute(
+        &mut context,
+        add_operator(&counter, &payer, operator.pubkey()),
+        &[],
+    )
+    .await;
+    let source = create_token_account(&mut context, &mint, &operator.pubkey(), 12).await;
+
+    execute(
+        &mut context,
+        increment_with_fee(&counter, &operator.pubkey(), None, &source),
+        &[&operator],
+    )
+    .await;
+    execute(
+        &mut context,
+        decrement_with_fee(&counter, &operator.pubkey(), Some(1), &source),
+        &[&operator],
+    )
+    .await;
+
+    assert_eq!(token_balance(&mut context, &source).await, Some(2));
+    assert_eq!(token_balance(&mut context, &vault).await, Some(10));
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn operations_without_fee_accounts_are_rejected() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, _) = create_fee_counter(&mut context, 5).await;
+
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, None),
+        &[],
+        CounterError::InvalidFeeAccounts,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn insufficient_balance_rejects_the_operation() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let source = create_token_account(&mut context, &mint, &payer, 4).await;
+
+    expect_error(
+        &mut context,
+        increment_with_fee(&counter, &payer, None, &source),
+        &[],
+        CounterError::InsufficientBalance,
+    )
+    .await;
+
+    assert_eq!(token_balance(&mut context, &source).await, Some(4));
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn fees_in_the_wrong_mint_are_rejected() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let other_mint = create_mint(&mut context).await;
+    let wrong_source = create_token_account(&mut context, &other_mint, &payer, 100).await;
+
+    expect_error(
+        &mut context,
+        increment_with_fee(&counter, &payer, None, &wrong_source),
+        &[],
+        CounterError::WrongFeeMint,
+    )
+    .await;
+
+    let source = create_token_account(&mut context, &mint, &payer, 5).await;
+    execute(
+        &mut context,
+        increment_with_fee(&counter, &payer, None, &source),
+        &[],
+    )
+    .await;
+    expect_error(
+        &mut context,
+        withdraw_fees(&counter, &payer, &wrong_source, 5),
+        &[],
+        CounterError::WrongFeeMint,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn authority_withdraws_collected_fees() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let (vault, _) = fee_vault_address(&counter);
+    let source = create_token_account(&mut context, &mint, &payer, 10).await;
+    let destination = create_token_account(&mut context, &mint, &payer, 0).await;
+    for _ in 0..2 {
+        execute(
+            &mut context,
+            increment_with_fee(&counter, &payer, None, &source),
+            &[],
+        )
+        .await;
+    }
+
+    let stranger = Keypair::new();
+    expect_error(
+        &mut context,
+        withdraw_fees(&counter, &stranger.pubkey(), &destination, 10),
+        &[&stranger],
+        CounterError::Unauthorized,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        withdraw_fees(&counter, &payer, &destination, 11),
+        &[],
+        CounterError::InsufficientBalance,
+    )
+    .await;
+
+    match execute(
+        &mut context,
+        withdraw_fees(&counter, &payer, &destination, 7),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::FeesWithdrawn(withdrawn)] => {
+            assert_eq!(withdrawn.counter, counter);
+            assert_eq!(withdrawn.destination, destination);
+            assert_eq!(withdrawn.amount, 7);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    assert_eq!(token_balance(&mut context, &vault).await, Some(3));
+    assert_eq!(token_balance(&mut context, &destination).await, Some(7));
+}
+
+#[tokio::test]
+async fn close_requires_an_empty_vault() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = create_fee_counter(&mut context, 5).await;
+    let (vault, _) = fee_vault_address(&counter);
+    let source = create_token_account(&mut context, &mint, &payer, 5).await;
+    execute(
+        &mut context,
+        increment_with_fee(&counter, &payer, None, &source),
+        &[],
+    )
+    .await;
+
+    expect_error(
+        &mut context,
+        close(&counter, &payer),
+        &[],
+        CounterError::InvalidFeeAccounts,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        close_with_fee(&counter, &payer),
+        &[],
+        CounterError::FeeVaultNotEmpty,
+    )
+    .await;
+
+    execute(
+        &mut context,
+        withdraw_fees(&counter, &payer, &source, 5),
+        &[],
+    )
+    .await;
+    execute(&mut context, close_with_fee(&counter, &payer), &[]).await;
+
+    assert_eq!(token_balance(&mut context, &vault).await, None);
+    assert!(context
+        .banks_client
+        .get_account(counter)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use common::{
+    create_counter, create_mint, create_token_account, execute, expect_error, fetch, setup,
+    store_metadata,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    decrement_with_tokens, increment, increment_with_tokens, set_collection_gate, set_gate,
+    OperatorTokens,
+};
+use counter_program::{CounterConfig, CounterError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+fn holding(gate_token_account: Pubkey) -> OperatorTokens {
+    OperatorTokens {
+        gate_token_account: Some(gate_token_account),
+        ..OperatorTokens::default()
+    }
+}
+
+fn holding_nft(gate_token_account: Pubkey, gate_metadata: Pubkey) -> OperatorTokens {
+    OperatorTokens {
+        gate_token_account: Some(gate_token_account),
+        gate_metadata: Some(gate_metadata),
+        ..OperatorTokens::default()
+    }
+}
+
+#[tokio::test]
+async fn set_gate_stores_and_clears_the_gate() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let mint = Pubkey::new_unique();
+
+    match execute(&mut context, set_gate(&counter, &payer, Some(mint), 3), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::GateConfigured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert_eq!(configured.gate_mint, Some(mint));
+            assert_eq!(configured.min_balance, 3);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!((stored.gate_mint, stored.min_balance), (Some(mint), 3));
+
+    execute(&mut context, set_gate(&counter, &payer, None, 3), &[]).await;
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!((stored.gate_mint, stored.min_balance), (None, 0));
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+}
+
+#[tokio::test]
+async fn gated_counter_requires_enough_of_the_gate_mint() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let mint = create_mint(&mut context).await;
+    let other_mint = create_mint(&mut context).await;
+    execute(&mut context, set_gate(&counter, &payer, Some(mint), 2), &[]).await;
+
+    let too_few = create_token_account(&mut context, &mint, &payer, 1).await;
+    let wrong_mint = create_token_account(&mut context, &other_mint, &payer, 5).await;
+    let not_owned = create_token_account(&mut context, &mint, &Pubkey::new_unique(), 5).await;
+    let enough = create_token_account(&mut context, &mint, &payer, 2).await;
+
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, None),
+        &[],
+        CounterError::GateNotSatisfied,
+    )
+    .await;
+    for account in [too_few, wrong_mint, not_owned] {
+        expect_error(
+            &mut context,
+            increment_with_tokens(&counter, &payer, None, holding(account)),
+            &[],
+            CounterError::GateNotSatisfied,
+        )
+        .await;
+    }
+
+    execute(
+        &mut context,
+        increment_with_tokens(&counter, &payer, None, holding(enough)),
+        &[],
+    )
+    .await;
+    execute(
+        &mut context,
+        decrement_with_tokens(&counter, &payer, Some(1), holding(enough)),
+        &[],
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn set_collection_gate_replaces_the_mint_gate() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let collection = Pubkey::new_unique();
+    execute(
+        &mut context,
+        set_gate(&counter, &payer, Some(Pubkey::new_unique()), 3),
+        &[],
+    )
+    .await;
+
+    match execute(
+        &mut context,
+        set_collection_gate(&counter, &payer, Some(collection)),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::CollectionGateConfigured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert_eq!(configured.collection, Some(collection));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.gate_collection, Some(collection));
+    assert_eq!((stored.gate_mint, stored.min_balance), (None, 0));
+
+    // And a mint gate, or none, replaces the collection gate
+    execute(&mut context, set_gate(&counter, &payer, None, 0), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.gate_collection, None);
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+}
+
+#[tokio::test]
+async fn collection_gate_requires_a_verified_nft_of_the_collection() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let collection = Pubkey::new_unique();
+    execute(
+        &mut context,
+        set_collection_gate(&counter, &payer, Some(collection)),
+        &[],
+    )
+    .await;
+
+    let nft = create_mint(&mut context).await;
+    let held = create_token_account(&mut context, &nft, &payer, 1).await;
+    let metadata = store_metadata(&mut context, &nft, &collection, true);
+
+    let unverified_nft = create_mint(&mut context).await;
+    let unverified = create_token_account(&mut context, &unverified_nft, &payer, 1).await;
+    let unverified_metadata = store_metadata(&mut context, &unverified_nft, &collection, false);
+
+    let foreign_nft = create_mint(&mut context).await;
+    let foreign = create_token_account(&mut context, &foreign_nft, &payer, 1).await;
+    let foreign_metadata = store_metadata(&mut context, &foreign_nft, &Pubkey::new_unique(), true);
+
+    let empty = create_token_account(&mut context, &nft, &payer, 0).await;
+    let not_owned = create_token_account(&mut context, &nft, &Pubkey::new_unique(), 1).await;
+
+    for tokens in [
+        OperatorTokens::default(),
+        holding(held),
+        holding_nft(unverified, unverified_metadata),
+        holding_nft(foreign, foreign_metadata),
+        // Metadata of an NFT other than the one held
+        holding_nft(foreign, metadata),
+        holding_nft(empty, metadata),
+        holding_nft(not_owned, metadata),
+    ] {
+        expect_error(
+            &mut context,
+            increment_with_tokens(&counter, &payer, None, tokens),
+            &[],
+            CounterError::GateNotSatisfied,
+        )
+        .await;
+    }
+
+    execute(
+        &mut context,
+        increment_with_tokens(&counter, &payer, None, holding_nft(held, metadata)),
+        &[],
+    )
+    .await;
+    execute(
+        &mut context,
+        decrement_with_tokens(&counter, &payer, Some(1), holding_nft(held, metadata)),
+        &[],
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
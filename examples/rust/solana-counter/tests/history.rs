@@ -0,0 +1,165 @@
+mod common;
+
+use common::{create_counter, execute, expect_error, fetch, setup};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    apply_ops, close, close_history, compare_and_set, decrement_by, fetch_history, history_address,
+    increment_by, init_history, reset, with_history,
+};
+use counter_program::{CounterConfig, CounterError, CounterOp, History, HistoryKind};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+/// Create counter `counter_id` with a history account and return its address
+async fn history_counter(context: &mut ProgramTestContext, counter_id: u64) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let counter = create_counter(context, counter_id, CounterConfig::default()).await;
+    execute(context, init_history(&counter, &payer), &[]).await;
+    counter
+}
+
+/// Fetch and decode the history account of `counter`
+async fn history(context: &mut ProgramTestContext, counter: &Pubkey) -> History {
+    fetch_history(&mut context.banks_client, &history_address(counter).0)
+        .await
+        .unwrap()
+}
+
+/// Kind, resulting value and delta of each entry, oldest first
+fn operations(history: &History) -> Vec<(Option<HistoryKind>, u64, i64)> {
+    history
+        .recent()
+        .map(|entry| (entry.history_kind(), entry.value, entry.delta))
+        .collect()
+}
+
+#[tokio::test]
+async fn updates_are_recorded_in_the_history() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    match execute(&mut context, init_history(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::HistoryInitialized(initialized)] => {
+            assert_eq!(initialized.counter, counter);
+            assert_eq!(initialized.history, history_address(&counter).0);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert!(fetch(&mut context, &counter).await.has_history);
+
+    for instruction in [
+        increment_by(&counter, &payer, 5),
+        decrement_by(&counter, &payer, 2),
+        compare_and_set(&counter, &payer, 3, 10),
+        reset(&counter, &payer),
+    ] {
+        execute(&mut context, with_history(instruction, &counter), &[]).await;
+    }
+
+    let history = history(&mut context, &counter).await;
+    assert_eq!(history.counter, counter);
+    assert_eq!(
+        operations(&history),
+        [
+            (Some(HistoryKind::Increment), 5, 5),
+            (Some(HistoryKind::Decrement), 3, -2),
+            (Some(HistoryKind::Set), 10, 7),
+            (Some(HistoryKind::Reset), 0, -10),
+        ]
+    );
+    assert!(history.recent().all(|entry| entry.actor == payer));
+}
+
+#[tokio::test]
+async fn history_keeps_only_the_latest_operations() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = history_counter(&mut context, 0).await;
+
+    execute(
+        &mut context,
+        with_history(
+            apply_ops(&counter, &payer, vec![CounterOp::Increment; 40], false),
+            &counter,
+        ),
+        &[],
+    )
+    .await;
+
+    let history = history(&mut context, &counter).await;
+    assert_eq!(history.len, 32);
+    let values: Vec<u64> = history.recent().map(|entry| entry.value).collect();
+    assert_eq!(values, (9..=40).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn updates_must_pass_the_counters_history() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = history_counter(&mut context, 0).await;
+    let other = history_counter(&mut context, 1).await;
+
+    expect_error(
+        &mut context,
+        increment_by(&counter, &payer, 1),
+        &[],
+        CounterError::InvalidHistoryAccount,
+    )
+    .await;
+
+    let mut foreign = increment_by(&counter, &payer, 1);
+    foreign
+        .accounts
+        .push(AccountMeta::new(history_address(&other).0, false));
+    expect_error(
+        &mut context,
+        foreign,
+        &[],
+        CounterError::InvalidHistoryAccount,
+    )
+    .await;
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+    assert!(operations(&history(&mut context, &other).await).is_empty());
+}
+
+#[tokio::test]
+async fn history_is_closed_before_the_counter() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = history_counter(&mut context, 0).await;
+
+    expect_error(
+        &mut context,
+        close(&counter, &payer),
+        &[],
+        CounterError::HistoryOpen,
+    )
+    .await;
+
+    match execute(&mut context, close_history(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::HistoryClosed(closed)] => assert_eq!(closed.counter, counter),
+        events => panic!("unexpected events: {events:?}"),
+    }
+    let address = history_address(&counter).0;
+    assert!(context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Updates go back to not taking the account
+    execute(&mut context, increment_by(&counter, &payer, 1), &[]).await;
+    assert!(!fetch(&mut context, &counter).await.has_history);
+    execute(&mut context, close(&counter, &payer), &[]).await;
+}
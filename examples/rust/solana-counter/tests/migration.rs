@@ -0,0 +1,305 @@
+mod common;
+
+use common::{
+    create_counter, execute, expect_error, fetch, funded_keypair, layout_data, setup, store_account,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    add_operator, counter_address, increment, increment_by, migrate, migrate_counter, PROGRAM_ID,
+};
+use counter_program::{Counter, CounterConfig, CounterError, CounterLayout, OverflowPolicy};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::Signer;
+use solana_sdk::system_program;
+
+/// Address counters used before they were addressed by id
+fn legacy_address(creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"counter", creator.as_ref()], &PROGRAM_ID)
+}
+
+/// Store `counter` in `layout` at the creator's legacy address
+fn store_legacy(
+    context: &mut ProgramTestContext,
+    creator: &Pubkey,
+    counter: &Counter,
+    layout: CounterLayout,
+    owner: &Pubkey,
+) -> Pubkey {
+    let address = legacy_address(creator).0;
+    store_account(context, &address, &layout_data(counter, layout), owner);
+    address
+}
+
+/// `template`, a counter owned by the payer, as `creator`'s counter at its
+/// legacy address
+fn legacy_counter(template: &Counter, creator: &Pubkey) -> Counter {
+    let mut counter = template.clone();
+    counter.bump = legacy_address(creator).1;
+    counter.creator = *creator;
+    counter
+}
+
+/// A counter owned by the payer to build legacy counters from
+async fn template(context: &mut ProgramTestContext) -> Counter {
+    let counter = create_counter(context, 0, CounterConfig::default()).await;
+    fetch(context, &counter).await
+}
+
+#[tokio::test]
+async fn migrates_every_legacy_layout() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let template = template(&mut context).await;
+    let pending = Pubkey::new_unique();
+
+    for layout in Counter::LEGACY_LAYOUTS.iter().copied() {
+        let creator = Pubkey::new_unique();
+        let mut counter = legacy_counter(&template, &creator);
+        counter.count = 15;
+        counter.pending_authority = Some(pending);
+        counter.min = 10;
+        counter.max = 20;
+        counter.overflow_policy = OverflowPolicy::Wrap;
+        let legacy = store_legacy(&mut context, &creator, &counter, layout, &PROGRAM_ID);
+
+        match execute(
+            &mut context,
+            migrate_counter(&legacy, &payer, creator, 3),
+            &[],
+        )
+        .await
+        .as_slice()
+        {
+            [CounterEvent::Migrated(migrated)] => {
+                assert_eq!(migrated.legacy_counter, legacy);
+                assert_eq!(migrated.counter, counter_address(&creator, 3).0);
+                assert_eq!(migrated.creator, creator);
+                assert_eq!(migrated.counter_id, 3);
+            }
+            events => panic!("unexpected events: {events:?}"),
+        }
+        assert!(context
+            .banks_client
+            .get_account(legacy)
+            .await
+            .unwrap()
+            .is_none());
+
+        let (address, bump) = counter_address(&creator, 3);
+        let migrated = fetch(&mut context, &address).await;
+        assert_eq!(migrated.count, 15);
+        assert_eq!(migrated.authority, payer);
+        assert_eq!(migrated.creator, creator);
+        assert_eq!(migrated.counter_id, 3);
+        assert_eq!(migrated.bump, bump);
+        assert_eq!(migrated.version, Counter::VERSION);
+        assert!(!migrated.paused);
+        assert!(migrated.operators.is_empty());
+        assert_eq!(migrated.fee, None);
+        assert_eq!(migrated.gate_mint, None);
+        assert_eq!(migrated.cooldown_slots, 0);
+
+        // Fields the layout predates take their defaults
+        let stores = |fields: usize| layout.fields >= fields;
+        assert_eq!(
+            migrated.pending_authority,
+            stores(5).then_some(pending),
+            "{layout:?}"
+        );
+        if stores(8) {
+            assert_eq!((migrated.min, migrated.max), (10, 20));
+            assert_eq!(migrated.overflow_policy, OverflowPolicy::Wrap);
+        } else {
+            assert_eq!((migrated.min, migrated.max), (0, u64::MAX));
+            assert_eq!(migrated.overflow_policy, OverflowPolicy::Error);
+        }
+    }
+}
+
+#[tokio::test]
+async fn rejects_accounts_that_are_not_legacy_counters() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let template = template(&mut context).await;
+    let layout = Counter::LEGACY_LAYOUTS[0];
+
+    // Owned by another program
+    let creator = Pubkey::new_unique();
+    let counter = legacy_counter(&template, &creator);
+    let address = store_legacy(
+        &mut context,
+        &creator,
+        &counter,
+        layout,
+        &system_program::ID,
+    );
+    expect_error(
+        &mut context,
+        migrate_counter(&address, &payer, creator, 0),
+        &[],
+        CounterError::InvalidLegacyAccount,
+    )
+    .await;
+
+    // Not one of the known layout sizes
+    let creator = Pubkey::new_unique();
+    let counter = legacy_counter(&template, &creator);
+    let address = legacy_address(&creator).0;
+    let mut data = layout_data(&counter, layout);
+    data.push(0);
+    store_account(&mut context, &address, &data, &PROGRAM_ID);
+    expect_error(
+        &mut context,
+        migrate_counter(&address, &payer, creator, 0),
+        &[],
+        CounterError::InvalidLegacyAccount,
+    )
+    .await;
+
+    // Signed by someone other than the stored authority
+    let creator = Pubkey::new_unique();
+    let mut foreign = legacy_counter(&template, &creator);
+    foreign.authority = Pubkey::new_unique();
+    let address = store_legacy(&mut context, &creator, &foreign, layout, &PROGRAM_ID);
+    expect_error(
+        &mut context,
+        migrate_counter(&address, &payer, creator, 0),
+        &[],
+        CounterError::Unauthorized,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn migrate_upgrades_version_1_counters_in_place() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let config = CounterConfig {
+        min: 0,
+        max: 100,
+        overflow_policy: OverflowPolicy::Saturate,
+    };
+    let counter = create_counter(&mut context, 0, config).await;
+    let operator = Pubkey::new_unique();
+    execute(&mut context, add_operator(&counter, &payer, operator), &[]).await;
+    execute(&mut context, increment_by(&counter, &payer, 7), &[]).await;
+
+    let before = fetch(&mut context, &counter).await;
+    assert_eq!(before.version, Counter::VERSION);
+    let unversioned = Counter::LAYOUTS
+        .iter()
+        .rfind(|layout| layout.version == 1)
+        .unwrap();
+    let data = layout_data(&before, *unversioned);
+    store_account(&mut context, &counter, &data, &PROGRAM_ID);
+
+    let migrator = funded_keypair(&mut context).await;
+    match execute(
+        &mut context,
+        migrate(&counter, &migrator.pubkey()),
+        &[&migrator],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::Upgraded(upgraded)] => {
+            assert_eq!(upgraded.counter, counter);
+            assert_eq!(upgraded.from_version, 1);
+            assert_eq!(upgraded.to_version, Counter::VERSION);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let account = context
+        .banks_client
+        .get_account(counter)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), Counter::LEN);
+    assert_eq!(
+        account.lamports,
+        Rent::default().minimum_balance(Counter::LEN)
+    );
+
+    let migrated = fetch(&mut context, &counter).await;
+    assert_eq!(migrated.version, Counter::VERSION);
+    assert_eq!(migrated.count, 7);
+    assert_eq!(migrated.authority, payer);
+    assert_eq!(migrated.bump, counter_address(&payer, 0).1);
+    assert_eq!((migrated.min, migrated.max), (0, 100));
+    assert_eq!(migrated.overflow_policy, OverflowPolicy::Saturate);
+    assert_eq!(migrated.operators, vec![operator]);
+    assert!(migrated.last_slot > 0);
+
+    execute(&mut context, increment(&counter, &payer, None), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 8);
+}
+
+#[tokio::test]
+async fn migrate_upgrades_every_layout_in_place() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+
+    for (counter_id, layout) in (0..).zip(Counter::LAYOUTS.iter().copied()) {
+        let counter = create_counter(&mut context, counter_id, CounterConfig::default()).await;
+        execute(&mut context, increment_by(&counter, &payer, 4), &[]).await;
+        let data = layout_data(&fetch(&mut context, &counter).await, layout);
+        store_account(&mut context, &counter, &data, &PROGRAM_ID);
+
+        match execute(&mut context, migrate(&counter, &payer), &[])
+            .await
+            .as_slice()
+        {
+            [CounterEvent::Upgraded(upgraded)] => {
+                assert_eq!(upgraded.from_version, layout.version);
+                assert_eq!(upgraded.to_version, Counter::VERSION);
+            }
+            events => panic!("unexpected events: {events:?}"),
+        }
+
+        let migrated = fetch(&mut context, &counter).await;
+        assert_eq!(migrated.version, Counter::VERSION);
+        assert_eq!(migrated.count, 4);
+        assert_eq!(migrated.counter_id, counter_id);
+        assert_eq!(migrated.permit_nonce, 0);
+        assert_eq!(migrated.pending_reset_at, None);
+        assert_eq!(migrated.window, None);
+        assert_eq!(migrated.previous_window_total, 0);
+        assert_eq!(migrated.reward_mint, None);
+        assert!(migrated.milestones.is_empty());
+        assert!(!migrated.has_history);
+        assert!(!migrated.require_scheduled_reset);
+        assert_eq!(migrated.gate_collection, None);
+    }
+}
+
+#[tokio::test]
+async fn migrate_rejects_current_and_misplaced_counters() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    // Already in the current layout
+    expect_error(
+        &mut context,
+        migrate(&counter, &payer),
+        &[],
+        CounterError::InvalidLegacyAccount,
+    )
+    .await;
+
+    // A version 1 counter away from the address its seeds derive
+    let misplaced = Pubkey::new_unique();
+    let data = layout_data(&fetch(&mut context, &counter).await, Counter::LAYOUTS[0]);
+    store_account(&mut context, &misplaced, &data, &PROGRAM_ID);
+    expect_error(
+        &mut context,
+        migrate(&misplaced, &payer),
+        &[],
+        CounterError::InvalidLegacyAccount,
+    )
+    .await;
+}
@@ -0,0 +1,287 @@
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use common::{
+    create_associated_token_account, create_counter, create_mint, create_mint_with_authority,
+    execute, expect_error, fetch, funded_keypair, setup, token_balance,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    add_operator, apply_ops, compare_and_set, counter_address, decrement_by, increment_by,
+    increment_by_with_tokens, reset, set_milestones, OperatorTokens,
+};
+use counter_program::{CounterConfig, CounterError, CounterOp, MilestoneConfig};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn milestone(threshold: u64, reward: u64) -> MilestoneConfig {
+    MilestoneConfig { threshold, reward }
+}
+
+fn rewarded_in(reward_mint: Pubkey) -> OperatorTokens {
+    OperatorTokens {
+        reward_mint: Some(reward_mint),
+        ..OperatorTokens::default()
+    }
+}
+
+/// Create counter 0 paying `milestones` in a new mint the counter controls,
+/// and return the counter and mint
+async fn milestone_counter(
+    context: &mut ProgramTestContext,
+    milestones: Vec<MilestoneConfig>,
+) -> (Pubkey, Pubkey) {
+    let payer = context.payer.pubkey();
+    let (address, _) = counter_address(&payer, 0);
+    let mint = create_mint_with_authority(context, &address).await;
+    let counter = create_counter(context, 0, CounterConfig::default()).await;
+    execute(
+        context,
+        set_milestones(&counter, &payer, Some(mint), milestones),
+        &[],
+    )
+    .await;
+    (counter, mint)
+}
+
+/// Thresholds of the milestones reached in `events`
+fn reached(events: &[CounterEvent]) -> Vec<u64> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            CounterEvent::MilestoneReached(reached) => Some(reached.threshold),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn increments_pay_each_milestone_they_reach() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let milestones = vec![milestone(3, 10), milestone(5, 20), milestone(10, 50)];
+    let (counter, mint) = milestone_counter(&mut context, milestones).await;
+    let operator = funded_keypair(&mut context).await;
+    execute(
+        &mut context,
+        add_operator(&counter, &payer, operator.pubkey()),
+        &[],
+    )
+    .await;
+    let payer_rewards = create_associated_token_account(&mut context, &mint, &payer).await;
+    let operator_rewards =
+        create_associated_token_account(&mut context, &mint, &operator.pubkey()).await;
+
+    // Reaching a threshold exactly counts
+    let events = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &payer, 3, rewarded_in(mint)),
+        &[],
+    )
+    .await;
+    match events.as_slice() {
+        [CounterEvent::Updated(_), CounterEvent::MilestoneReached(reached)] => {
+            assert_eq!(reached.counter, counter);
+            assert_eq!(reached.recipient, payer);
+            assert_eq!((reached.threshold, reached.reward), (3, 10));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert_eq!(token_balance(&mut context, &payer_rewards).await, Some(10));
+
+    // One increment can pass several thresholds
+    let events = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &operator.pubkey(), 8, rewarded_in(mint)),
+        &[&operator],
+    )
+    .await;
+    assert_eq!(reached(&events), [5, 10]);
+    assert_eq!(
+        token_balance(&mut context, &operator_rewards).await,
+        Some(70)
+    );
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 11);
+    assert!(stored.milestones.iter().all(|milestone| milestone.reached));
+}
+
+#[tokio::test]
+async fn milestones_pay_out_only_once() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = milestone_counter(&mut context, vec![milestone(2, 5)]).await;
+    let rewards = create_associated_token_account(&mut context, &mint, &payer).await;
+
+    let first = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &payer, 2, rewarded_in(mint)),
+        &[],
+    )
+    .await;
+    assert_eq!(reached(&first), [2]);
+
+    // Back below the threshold and up again
+    execute(&mut context, decrement_by(&counter, &payer, 1), &[]).await;
+    let again = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &payer, 1, rewarded_in(mint)),
+        &[],
+    )
+    .await;
+    assert!(reached(&again).is_empty());
+
+    // Re-registering the same threshold does not re-arm it
+    execute(&mut context, reset(&counter, &payer), &[]).await;
+    execute(
+        &mut context,
+        set_milestones(&counter, &payer, Some(mint), vec![milestone(2, 5)]),
+        &[],
+    )
+    .await;
+    let after_reset = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &payer, 2, rewarded_in(mint)),
+        &[],
+    )
+    .await;
+    assert!(reached(&after_reset).is_empty());
+
+    assert_eq!(token_balance(&mut context, &rewards).await, Some(5));
+    assert_eq!(fetch(&mut context, &counter).await.count, 2);
+}
+
+#[tokio::test]
+async fn milestones_passed_without_a_reward_are_not_paid_later() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let milestones = vec![milestone(2, 5), milestone(4, 5)];
+    let (counter, mint) = milestone_counter(&mut context, milestones).await;
+    let rewards = create_associated_token_account(&mut context, &mint, &payer).await;
+
+    // A batch and a compare-and-set pass the thresholds without paying
+    let ops = vec![
+        CounterOp::Increment,
+        CounterOp::IncrementBy(2),
+        CounterOp::Decrement,
+    ];
+    let events = execute(&mut context, apply_ops(&counter, &payer, ops, false), &[]).await;
+    assert!(reached(&events).is_empty());
+    let events = execute(&mut context, compare_and_set(&counter, &payer, 2, 4), &[]).await;
+    assert!(reached(&events).is_empty());
+    let stored = fetch(&mut context, &counter).await;
+    assert!(stored.milestones.iter().all(|milestone| milestone.reached));
+
+    // Back below both thresholds and up again through an increment
+    execute(&mut context, decrement_by(&counter, &payer, 3), &[]).await;
+    let again = execute(
+        &mut context,
+        increment_by_with_tokens(&counter, &payer, 3, rewarded_in(mint)),
+        &[],
+    )
+    .await;
+    assert!(reached(&again).is_empty());
+
+    assert_eq!(token_balance(&mut context, &rewards).await, Some(0));
+    assert_eq!(fetch(&mut context, &counter).await.count, 4);
+}
+
+#[tokio::test]
+async fn reaching_a_milestone_requires_the_reward_accounts() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let (counter, mint) = milestone_counter(&mut context, vec![milestone(5, 1)]).await;
+
+    // Below the threshold no reward accounts are needed
+    execute(&mut context, increment_by(&counter, &payer, 4), &[]).await;
+    expect_error(
+        &mut context,
+        increment_by(&counter, &payer, 1),
+        &[],
+        CounterError::InvalidRewardAccounts,
+    )
+    .await;
+
+    // A token account other than the caller's associated one
+    let other = create_associated_token_account(&mut context, &mint, &Pubkey::new_unique()).await;
+    let own = get_associated_token_address(&payer, &mint);
+    let mut ix = increment_by_with_tokens(&counter, &payer, 1, rewarded_in(mint));
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == own) {
+        meta.pubkey = other;
+    }
+    expect_error(&mut context, ix, &[], CounterError::InvalidRewardAccounts).await;
+
+    assert_eq!(fetch(&mut context, &counter).await.count, 4);
+}
+
+#[tokio::test]
+async fn set_milestones_validates_the_milestones_and_mint() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let mint = create_mint_with_authority(&mut context, &counter).await;
+    let foreign_mint = create_mint(&mut context).await;
+    let stranger = Keypair::new();
+
+    for (reward_mint, milestones, error) in [
+        (
+            Some(mint),
+            vec![milestone(5, 1), milestone(5, 2)],
+            CounterError::InvalidMilestones,
+        ),
+        (
+            Some(mint),
+            (1..=6).map(|threshold| milestone(threshold, 1)).collect(),
+            CounterError::TooManyMilestones,
+        ),
+        (
+            Some(foreign_mint),
+            vec![milestone(5, 1)],
+            CounterError::InvalidRewardMint,
+        ),
+        (None, vec![milestone(5, 1)], CounterError::InvalidRewardMint),
+    ] {
+        expect_error(
+            &mut context,
+            set_milestones(&counter, &payer, reward_mint, milestones),
+            &[],
+            error,
+        )
+        .await;
+    }
+    expect_error(
+        &mut context,
+        set_milestones(&counter, &stranger.pubkey(), Some(mint), vec![]),
+        &[&stranger],
+        CounterError::Unauthorized,
+    )
+    .await;
+
+    match execute(
+        &mut context,
+        set_milestones(&counter, &payer, Some(mint), vec![milestone(5, 1)]),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::MilestonesConfigured(configured)] => {
+            assert_eq!(configured.reward_mint, Some(mint));
+            assert_eq!(configured.milestones.len(), 1);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    // An empty list stops the rewards
+    execute(
+        &mut context,
+        set_milestones(&counter, &payer, None, vec![]),
+        &[],
+    )
+    .await;
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.reward_mint, None);
+    assert!(stored.milestones.is_empty());
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use common::{
+    assert_error, assert_success, clock, create_counter, execute, fetch, send_all, setup,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{accept_authority, increment_with_permit, pause, propose_authority};
+use counter_program::{CounterConfig, CounterError, Permit};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+/// Create a counter whose authority is a new keypair without any SOL
+async fn user_counter(context: &mut ProgramTestContext) -> (Pubkey, Keypair) {
+    let payer = context.payer.pubkey();
+    let counter = create_counter(context, 0, CounterConfig::default()).await;
+    let user = Keypair::new();
+    execute(
+        context,
+        propose_authority(&counter, &payer, user.pubkey()),
+        &[],
+    )
+    .await;
+    execute(
+        context,
+        accept_authority(&counter, &user.pubkey()),
+        &[&user],
+    )
+    .await;
+    (counter, user)
+}
+
+/// A permit for `counter` valid for another hour
+async fn permit(
+    context: &mut ProgramTestContext,
+    counter: Pubkey,
+    nonce: u64,
+    delta: u64,
+) -> Permit {
+    let clock = clock(context).await;
+    Permit {
+        counter,
+        nonce,
+        delta,
+        expiry: clock.unix_timestamp + 3_600,
+    }
+}
+
+#[tokio::test]
+async fn relayer_submits_a_signed_permit() {
+    let mut context = setup().await;
+    let (counter, user) = user_counter(&mut context).await;
+    let permit = permit(&mut context, counter, 0, 5).await;
+    let signature = user.sign_message(&permit.message());
+
+    // Only the payer, acting as relayer, signs the transaction
+    let outcome = send_all(
+        &mut context,
+        &increment_with_permit(&user.pubkey(), &permit, &signature),
+        &[],
+    )
+    .await;
+    match assert_success(&outcome).as_slice() {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!(updated.counter, counter);
+            assert_eq!((updated.previous_value, updated.new_value), (0, 5));
+            assert_eq!(updated.delta, 5);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 5);
+    assert_eq!(stored.permit_nonce, 1);
+}
+
+#[tokio::test]
+async fn permits_cannot_be_replayed() {
+    let mut context = setup().await;
+    let (counter, user) = user_counter(&mut context).await;
+    let first = permit(&mut context, counter, 0, 1).await;
+    let ahead = permit(&mut context, counter, 2, 1).await;
+    let instructions =
+        increment_with_permit(&user.pubkey(), &first, &user.sign_message(&first.message()));
+
+    assert_success(&send_all(&mut context, &instructions, &[]).await);
+    assert_error(
+        &send_all(&mut context, &instructions, &[]).await,
+        CounterError::InvalidPermitNonce,
+    );
+    assert_error(
+        &send_all(
+            &mut context,
+            &increment_with_permit(&user.pubkey(), &ahead, &user.sign_message(&ahead.message())),
+            &[],
+        )
+        .await,
+        CounterError::InvalidPermitNonce,
+    );
+    assert_eq!(fetch(&mut context, &counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn expired_permits_are_rejected() {
+    let mut context = setup().await;
+    let (counter, user) = user_counter(&mut context).await;
+    let mut expired = permit(&mut context, counter, 0, 1).await;
+    expired.expiry -= 7_200;
+
+    assert_error(
+        &send_all(
+            &mut context,
+            &increment_with_permit(
+                &user.pubkey(),
+                &expired,
+                &user.sign_message(&expired.message()),
+            ),
+            &[],
+        )
+        .await,
+        CounterError::PermitExpired,
+    );
+}
+
+#[tokio::test]
+async fn permits_must_match_the_authority_signature() {
+    let mut context = setup().await;
+    let (counter, user) = user_counter(&mut context).await;
+    let permit = permit(&mut context, counter, 0, 5).await;
+
+    // Signed by someone other than the authority
+    let stranger = Keypair::new();
+    assert_error(
+        &send_all(
+            &mut context,
+            &increment_with_permit(
+                &stranger.pubkey(),
+                &permit,
+                &stranger.sign_message(&permit.message()),
+            ),
+            &[],
+        )
+        .await,
+        CounterError::InvalidPermit,
+    );
+
+    // Submitted with a larger delta than the authority signed
+    let [verify, _] = increment_with_permit(
+        &user.pubkey(),
+        &permit,
+        &user.sign_message(&permit.message()),
+    );
+    let inflated = Permit {
+        delta: 50,
+        ..permit
+    };
+    let [_, submit] = increment_with_permit(&user.pubkey(), &inflated, &Signature::default());
+    assert_error(
+        &send_all(&mut context, &[verify, submit.clone()], &[]).await,
+        CounterError::InvalidPermit,
+    );
+
+    // Without the signature check in front of it
+    assert_error(
+        &send_all(&mut context, &[submit], &[]).await,
+        CounterError::InvalidPermit,
+    );
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn permits_respect_pause() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, pause(&counter, &payer), &[]).await;
+
+    let permit = permit(&mut context, counter, 0, 1).await;
+    let signature = context.payer.sign_message(&permit.message());
+    assert_error(
+        &send_all(
+            &mut context,
+            &increment_with_permit(&payer, &permit, &signature),
+            &[],
+        )
+        .await,
+        CounterError::Paused,
+    );
+}
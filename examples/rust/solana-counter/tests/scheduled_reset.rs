@@ -0,0 +1,223 @@
+mod common;
+
+use common::{advance_clock, clock, create_counter, execute, expect_error, fetch, setup};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    apply_ops, cancel_reset, compare_and_set, execute_reset, increment_by, pause, reset,
+    schedule_reset, set_require_scheduled_reset,
+};
+use counter_program::{Counter, CounterConfig, CounterError, CounterOp};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A counter at 10 with a reset scheduled `delay_secs` from now
+async fn scheduled_counter(context: &mut ProgramTestContext, delay_secs: u32) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let counter = create_counter(context, 0, CounterConfig::default()).await;
+    execute(context, increment_by(&counter, &payer, 10), &[]).await;
+    execute(context, schedule_reset(&counter, &payer, delay_secs), &[]).await;
+    counter
+}
+
+#[tokio::test]
+async fn scheduled_reset_unlocks_after_the_delay() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, increment_by(&counter, &payer, 10), &[]).await;
+
+    let clock = clock(&mut context).await;
+    let unlock_at = match execute(&mut context, schedule_reset(&counter, &payer, 600), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::ResetScheduled(scheduled)] => {
+            assert_eq!(scheduled.counter, counter);
+            assert!(scheduled.unlock_at >= clock.unix_timestamp + 600);
+            scheduled.unlock_at
+        }
+        events => panic!("unexpected events: {events:?}"),
+    };
+    assert_eq!(
+        fetch(&mut context, &counter).await.pending_reset_at,
+        Some(unlock_at)
+    );
+
+    expect_error(
+        &mut context,
+        schedule_reset(&counter, &payer, 60),
+        &[],
+        CounterError::ResetAlreadyScheduled,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        execute_reset(&counter, &payer),
+        &[],
+        CounterError::ResetLocked,
+    )
+    .await;
+
+    advance_clock(&mut context, 600).await;
+    match execute(&mut context, execute_reset(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Reset(reset)] => assert_eq!(reset.counter, counter),
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 0);
+    assert_eq!(stored.pending_reset_at, None);
+    expect_error(
+        &mut context,
+        execute_reset(&counter, &payer),
+        &[],
+        CounterError::NoPendingReset,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cancelled_reset_leaves_the_count_alone() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = scheduled_counter(&mut context, 600).await;
+
+    // Incident response: pause first, then withdraw the reset
+    execute(&mut context, pause(&counter, &payer), &[]).await;
+    match execute(&mut context, cancel_reset(&counter, &payer), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::ResetCancelled(cancelled)] => {
+            assert_eq!(cancelled.counter, counter);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    expect_error(
+        &mut context,
+        cancel_reset(&counter, &payer),
+        &[],
+        CounterError::NoPendingReset,
+    )
+    .await;
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 10);
+    assert_eq!(stored.pending_reset_at, None);
+}
+
+#[tokio::test]
+async fn scheduled_reset_expires_after_its_window() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = scheduled_counter(&mut context, 60).await;
+
+    advance_clock(&mut context, 60 + Counter::RESET_WINDOW_SECS + 1).await;
+    expect_error(
+        &mut context,
+        execute_reset(&counter, &payer),
+        &[],
+        CounterError::ResetWindowClosed,
+    )
+    .await;
+
+    // Cancelling clears the stale schedule so a new one can be made
+    execute(&mut context, cancel_reset(&counter, &payer), &[]).await;
+    execute(&mut context, schedule_reset(&counter, &payer, 0), &[]).await;
+    execute(&mut context, execute_reset(&counter, &payer), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn only_the_authority_manages_scheduled_resets() {
+    let mut context = setup().await;
+    let counter = scheduled_counter(&mut context, 0).await;
+    let stranger = Keypair::new();
+
+    for instruction in [
+        schedule_reset(&counter, &stranger.pubkey(), 0),
+        execute_reset(&counter, &stranger.pubkey()),
+        cancel_reset(&counter, &stranger.pubkey()),
+        set_require_scheduled_reset(&counter, &stranger.pubkey(), false),
+    ] {
+        expect_error(
+            &mut context,
+            instruction,
+            &[&stranger],
+            CounterError::Unauthorized,
+        )
+        .await;
+    }
+    assert_eq!(fetch(&mut context, &counter).await.count, 10);
+}
+
+#[tokio::test]
+async fn required_scheduled_resets_reject_the_instant_paths() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, increment_by(&counter, &payer, 10), &[]).await;
+
+    match execute(
+        &mut context,
+        set_require_scheduled_reset(&counter, &payer, true),
+        &[],
+    )
+    .await
+    .as_slice()
+    {
+        [CounterEvent::ResetLockConfigured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert!(configured.require_scheduled_reset);
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    assert!(fetch(&mut context, &counter).await.require_scheduled_reset);
+
+    for instruction in [
+        reset(&counter, &payer),
+        compare_and_set(&counter, &payer, 10, 0),
+        apply_ops(&counter, &payer, vec![CounterOp::Reset], false),
+        apply_ops(
+            &counter,
+            &payer,
+            vec![CounterOp::Increment, CounterOp::Set(0)],
+            false,
+        ),
+    ] {
+        expect_error(
+            &mut context,
+            instruction,
+            &[],
+            CounterError::ScheduledResetRequired,
+        )
+        .await;
+    }
+    assert_eq!(fetch(&mut context, &counter).await.count, 10);
+
+    // Increments and decrements in a batch are still allowed
+    execute(
+        &mut context,
+        apply_ops(&counter, &payer, vec![CounterOp::DecrementBy(4)], false),
+        &[],
+    )
+    .await;
+
+    execute(&mut context, schedule_reset(&counter, &payer, 0), &[]).await;
+    execute(&mut context, execute_reset(&counter, &payer), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+
+    execute(
+        &mut context,
+        set_require_scheduled_reset(&counter, &payer, false),
+        &[],
+    )
+    .await;
+    execute(&mut context, increment_by(&counter, &payer, 3), &[]).await;
+    execute(&mut context, reset(&counter, &payer), &[]).await;
+    assert_eq!(fetch(&mut context, &counter).await.count, 0);
+}
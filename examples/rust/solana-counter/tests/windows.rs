@@ -0,0 +1,258 @@
+mod common;
+
+use common::{
+    advance_clock, advance_epochs, advance_slots, clock, create_counter, execute, expect_error,
+    fetch, setup,
+};
+use counter_client::events::CounterEvent;
+use counter_client::{
+    apply_ops, compare_and_set, decrement, decrement_by, fetch_history, history_address, increment,
+    increment_by, init_history, set_window, with_history,
+};
+use counter_program::{CounterConfig, CounterError, CounterOp, HistoryKind, RollingWindow};
+use solana_sdk::signature::{Keypair, Signer};
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[tokio::test]
+async fn count_rolls_over_when_a_day_ends() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+
+    let window = Some(RollingWindow::Seconds(DAY));
+    let window_index = match execute(&mut context, set_window(&counter, &payer, window), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::WindowConfigured(configured)] => {
+            assert_eq!(configured.counter, counter);
+            assert_eq!(configured.window, window);
+            configured.window_index
+        }
+        events => panic!("unexpected events: {events:?}"),
+    };
+    execute(&mut context, increment_by(&counter, &payer, 7), &[]).await;
+
+    advance_clock(&mut context, DAY as i64).await;
+    match execute(&mut context, increment_by(&counter, &payer, 2), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::WindowClosed(closed), CounterEvent::Updated(updated)] => {
+            assert_eq!(closed.counter, counter);
+            assert_eq!(closed.window_index, window_index);
+            assert_eq!(closed.total, 7);
+            assert_eq!((updated.previous_value, updated.new_value), (0, 2));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 2);
+    assert_eq!(stored.window_index, window_index + 1);
+    assert_eq!(stored.previous_window_total, 7);
+}
+
+#[tokio::test]
+async fn batches_roll_over_before_their_first_op() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(&mut context, init_history(&counter, &payer), &[]).await;
+    execute(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Seconds(DAY))),
+        &[],
+    )
+    .await;
+    let increment = with_history(increment_by(&counter, &payer, 7), &counter);
+    execute(&mut context, increment, &[]).await;
+    let window_index = fetch(&mut context, &counter).await.window_index;
+
+    advance_slots(&mut context, 10).await;
+    advance_clock(&mut context, DAY as i64).await;
+    let ops = vec![CounterOp::IncrementBy(3), CounterOp::Decrement];
+    let batch = with_history(apply_ops(&counter, &payer, ops, true), &counter);
+    match execute(&mut context, batch, &[]).await.as_slice() {
+        [CounterEvent::WindowClosed(closed), CounterEvent::Updated(first), CounterEvent::Updated(second), CounterEvent::OpsApplied(applied)] =>
+        {
+            assert_eq!(closed.window_index, window_index);
+            assert_eq!(closed.total, 7);
+            assert_eq!((first.previous_value, first.new_value), (0, 3));
+            assert_eq!((second.previous_value, second.new_value), (3, 2));
+            assert_eq!((applied.previous_value, applied.new_value), (0, 2));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 2);
+    assert_eq!(stored.window_index, window_index + 1);
+    assert_eq!(stored.previous_window_total, 7);
+    assert_eq!(stored.last_slot, clock(&mut context).await.slot);
+
+    let history = fetch_history(&mut context.banks_client, &history_address(&counter).0)
+        .await
+        .unwrap();
+    let kinds: Vec<_> = history
+        .recent()
+        .map(|entry| (entry.history_kind(), entry.value))
+        .collect();
+    assert_eq!(
+        kinds[kinds.len() - 3..],
+        [
+            (Some(HistoryKind::Rollover), 0),
+            (Some(HistoryKind::Increment), 3),
+            (Some(HistoryKind::Decrement), 2),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn expected_values_are_checked_after_the_roll() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Seconds(DAY))),
+        &[],
+    )
+    .await;
+    execute(&mut context, increment_by(&counter, &payer, 5), &[]).await;
+
+    // The old window's count is stale once the window has ended
+    advance_clock(&mut context, DAY as i64).await;
+    expect_error(
+        &mut context,
+        increment(&counter, &payer, Some(5)),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        decrement(&counter, &payer, Some(5)),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        compare_and_set(&counter, &payer, 5, 9),
+        &[],
+        CounterError::StaleValue,
+    )
+    .await;
+
+    match execute(&mut context, increment(&counter, &payer, Some(0)), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::WindowClosed(closed), CounterEvent::Updated(updated)] => {
+            assert_eq!(closed.total, 5);
+            assert_eq!((updated.previous_value, updated.new_value), (0, 1));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    advance_clock(&mut context, DAY as i64).await;
+    match execute(&mut context, compare_and_set(&counter, &payer, 0, 9), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::WindowClosed(closed), CounterEvent::Updated(updated)] => {
+            assert_eq!(closed.total, 1);
+            assert_eq!((updated.previous_value, updated.new_value), (0, 9));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!((stored.count, stored.previous_window_total), (9, 1));
+}
+
+#[tokio::test]
+async fn count_carries_on_within_a_window() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Epochs(2))),
+        &[],
+    )
+    .await;
+
+    execute(&mut context, increment_by(&counter, &payer, 5), &[]).await;
+    let clock = clock(&mut context).await;
+    if clock.epoch % 2 == 0 {
+        advance_epochs(&mut context, 1).await;
+    }
+    match execute(&mut context, decrement_by(&counter, &payer, 1), &[])
+        .await
+        .as_slice()
+    {
+        [CounterEvent::Updated(updated)] => {
+            assert_eq!((updated.previous_value, updated.new_value), (5, 4));
+        }
+        events => panic!("unexpected events: {events:?}"),
+    }
+
+    // A window passing without activity is archived on the next update
+    advance_epochs(&mut context, 4).await;
+    execute(&mut context, increment_by(&counter, &payer, 1), &[]).await;
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 1);
+    assert_eq!(stored.previous_window_total, 4);
+}
+
+#[tokio::test]
+async fn removing_the_window_stops_the_roll() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    execute(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Seconds(60))),
+        &[],
+    )
+    .await;
+    execute(&mut context, set_window(&counter, &payer, None), &[]).await;
+
+    execute(&mut context, increment_by(&counter, &payer, 3), &[]).await;
+    advance_clock(&mut context, 120).await;
+    execute(&mut context, increment_by(&counter, &payer, 3), &[]).await;
+
+    let stored = fetch(&mut context, &counter).await;
+    assert_eq!(stored.count, 6);
+    assert_eq!(stored.window, None);
+}
+
+#[tokio::test]
+async fn set_window_is_authority_only_and_rejects_empty_windows() {
+    let mut context = setup().await;
+    let payer = context.payer.pubkey();
+    let counter = create_counter(&mut context, 0, CounterConfig::default()).await;
+    let stranger = Keypair::new();
+
+    expect_error(
+        &mut context,
+        set_window(&counter, &payer, Some(RollingWindow::Seconds(0))),
+        &[],
+        CounterError::InvalidWindow,
+    )
+    .await;
+    expect_error(
+        &mut context,
+        set_window(
+            &counter,
+            &stranger.pubkey(),
+            Some(RollingWindow::Seconds(DAY)),
+        ),
+        &[&stranger],
+        CounterError::Unauthorized,
+    )
+    .await;
+    assert_eq!(fetch(&mut context, &counter).await.window, None);
+}